@@ -0,0 +1,244 @@
+use crate::{Map, MoveDirection, Position};
+use crate::board::Board;
+use crate::deadlock::{self, DeadSquares, Deadlock};
+
+/// Deadlock checks for groups of blocks on one level: frozen blocks and closed corrals,
+/// on top of the single block checks in [`deadlock`].
+#[derive(Debug, Clone)]
+pub struct DeadlockAnalyzer {
+    board: Board,
+    dead_squares: DeadSquares,
+}
+
+impl DeadlockAnalyzer {
+    /// Prepares the analysis of positions on the level of `map`, ignoring its blocks.
+    pub fn new(map: &Map) -> DeadlockAnalyzer {
+        let board = Board::new(map);
+        DeadlockAnalyzer { dead_squares: DeadSquares::from_board(&board), board }
+    }
+
+    /// Dead squares of the level.
+    pub fn dead_squares(&self) -> &DeadSquares {
+        &self.dead_squares
+    }
+
+    pub(crate) fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Checks the live map for any deadlock: blocks on dead squares, frozen 2x2 squares,
+    /// frozen block groups and closed corrals.
+    pub fn analyse(&self, map: &Map) -> Option<Deadlock> {
+        if let Some(deadlock) = deadlock::find_deadlock(map, self.dead_squares()) {
+            return Some(deadlock);
+        }
+        let board = &self.board;
+        let box_mask = board.box_mask(board.block_cells(map));
+        board.block_cells(map)
+            .find(|cell| self.is_frozen_deadlock(&box_mask, *cell))
+            .map(|cell| Deadlock::FrozenBlocks(board.position(cell)))
+            .or_else(|| self.find_closed_corral(&box_mask, map))
+    }
+
+    /// Checks the live map right after the block now at `pushed` was pushed.
+    ///
+    /// Only deadlocks involving that block are looked for, apart from closed corrals.
+    pub fn analyse_push(&self, map: &Map, pushed: &Position) -> Option<Deadlock> {
+        if let Some(deadlock) = deadlock::check_block(map, self.dead_squares(), pushed) {
+            return Some(deadlock);
+        }
+        let box_mask = self.board.box_mask(self.board.block_cells(map));
+        if self.is_frozen_deadlock(&box_mask, self.board.index(pushed)) {
+            return Some(Deadlock::FrozenBlocks(*pushed));
+        }
+        self.find_closed_corral(&box_mask, map)
+    }
+
+    fn find_closed_corral(&self, box_mask: &[bool], map: &Map) -> Option<Deadlock> {
+        let reachable = self.board.reachable(box_mask, self.board.index(&map.player_position));
+        self.closed_corral(box_mask, &reachable).map(|cell| Deadlock::ClosedCorral(self.board.position(cell)))
+    }
+
+    /// Whether the box on `cell` is frozen together with boxes off the target zones. This covers
+    /// frozen 2x2 squares as well. Used by the solver, `box_mask` marks the box cells.
+    pub(crate) fn is_frozen_deadlock(&self, box_mask: &[bool], cell: usize) -> bool {
+        let mut frozen = Vec::new();
+        self.is_frozen(box_mask, cell, &mut Vec::new(), &mut frozen)
+            && frozen.iter().any(|cell| !self.board.goals[*cell])
+    }
+
+    /// Whether the box on `cell` can never move again. Boxes checked further up count as walls
+    /// and are listed in `walls`, boxes found frozen are added to `frozen`.
+    fn is_frozen(&self, box_mask: &[bool], cell: usize, walls: &mut Vec<usize>, frozen: &mut Vec<usize>) -> bool {
+        let checked = frozen.len();
+        walls.push(cell);
+        let is_frozen = self.is_axis_blocked(box_mask, cell, [MoveDirection::Left, MoveDirection::Right], walls, frozen)
+            && self.is_axis_blocked(box_mask, cell, [MoveDirection::Up, MoveDirection::Down], walls, frozen);
+        walls.pop();
+        if is_frozen {
+            frozen.push(cell);
+        } else {
+            frozen.truncate(checked);
+        }
+        is_frozen
+    }
+
+    /// Whether the box on `cell` cannot be pushed along the axis of `movedirs`: a wall on one side,
+    /// dead squares on both sides or a frozen box on one side.
+    fn is_axis_blocked(&self, box_mask: &[bool], cell: usize, movedirs: [MoveDirection; 2], walls: &mut Vec<usize>,
+            frozen: &mut Vec<usize>) -> bool {
+        let board = &self.board;
+        let [first, second] = match movedirs.map(|movedir| board.step(cell, movedir)) {
+            [Some(first), Some(second)] => [first, second],
+            _ => return true,
+        };
+        if [first, second].iter().any(|side| !board.floor[*side] || walls.contains(side)) {
+            return true;
+        }
+        if board.is_dead(first) && board.is_dead(second) {
+            return true;
+        }
+        [first, second].iter().any(|side| box_mask[*side] && self.is_frozen(box_mask, *side, walls, frozen))
+    }
+
+    /// Finds an area the player cannot reach, including the boxes in and around it, in which
+    /// no box can be pushed, while a target zone in it is empty or a box is off the target zones.
+    /// Returns the first cell of that area. `reachable` are the cells the player can walk to.
+    ///
+    /// The area is only entered by pushing one of its boxes from a cell the player reaches now,
+    /// so if none of them can be pushed without hitting a wall, a dead square or another of
+    /// its boxes, it stays like this for good.
+    pub(crate) fn closed_corral(&self, box_mask: &[bool], reachable: &[bool]) -> Option<usize> {
+        let board = &self.board;
+        let can_push = |cell: usize, movedir: MoveDirection| {
+            match (board.step(cell, movedir.opposite()), board.step(cell, movedir)) {
+                (Some(from), Some(to)) => reachable[from] && board.floor[to] && !box_mask[to] && !board.is_dead(to),
+                _ => false,
+            }
+        };
+        self.corrals(reachable).into_iter()
+            .find(|cells| {
+                let has_work = cells.iter().any(|cell| board.goals[*cell] != box_mask[*cell]);
+                let is_closed = !cells.iter()
+                    .filter(|cell| box_mask[**cell])
+                    .any(|cell| MoveDirection::ALL.iter().any(|movedir| can_push(*cell, *movedir)));
+                has_work && is_closed
+            })
+            .map(|cells| cells[0])
+    }
+
+    /// The areas of floor the player cannot reach, boxes included, each as a list of cells
+    /// starting with its top-left cell.
+    pub(crate) fn corrals(&self, reachable: &[bool]) -> Vec<Vec<usize>> {
+        let board = &self.board;
+        let mut corrals = Vec::new();
+        let mut in_corral = vec![false; board.len()];
+        for start in 0..board.len() {
+            if !board.floor[start] || reachable[start] || in_corral[start] {
+                continue;
+            }
+            let mut cells = vec![start];
+            let mut stack = vec![start];
+            in_corral[start] = true;
+            while let Some(cell) = stack.pop() {
+                for next in board.neighbours[cell].iter().flatten() {
+                    if board.floor[*next] && !reachable[*next] && !in_corral[*next] {
+                        in_corral[*next] = true;
+                        cells.push(*next);
+                        stack.push(*next);
+                    }
+                }
+            }
+            corrals.push(cells);
+        }
+        corrals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movement;
+    use crate::test_util::parse;
+
+    #[test]
+    fn test_shipped_levels_start_without_deadlock() {
+        let mut map_manager = crate::MapManager::new();
+        map_manager.read_maps(crate::EmbeddedMapContentProvider {}).unwrap();
+        for map in map_manager.maps.iter() {
+            assert_eq!(None, DeadlockAnalyzer::new(map).analyse(map), "map {}", map.id);
+        }
+    }
+
+    #[test]
+    fn test_frozen_blocks() {
+        // Neither block is on a dead square or in a 2x2 square, but each keeps the other in place.
+        let map = parse(&[
+            "XXXXXXX",
+            "X  .  X",
+            "XX*   X",
+            "X *X  X",
+            "X @ . X",
+            "XXXXXXX",
+        ]);
+        let analyzer = DeadlockAnalyzer::new(&map);
+        assert_eq!(None, deadlock::find_deadlock(&map, analyzer.dead_squares()));
+        assert_eq!(Some(Deadlock::FrozenBlocks(Position { x: 2, y: 2 })), analyzer.analyse(&map));
+        assert_eq!(Some(Deadlock::FrozenBlocks(Position { x: 2, y: 3 })), analyzer.analyse_push(&map, &Position { x: 2, y: 3 }));
+    }
+
+    #[test]
+    fn test_frozen_blocks_on_targets() {
+        let map = parse(&[
+            "XXXXXXX",
+            "X     X",
+            "XX&   X",
+            "X &X  X",
+            "X @   X",
+            "XXXXXXX",
+        ]);
+        assert_eq!(None, DeadlockAnalyzer::new(&map).analyse(&map));
+    }
+
+    #[test]
+    fn test_blocks_which_can_still_move() {
+        let map = parse(&[
+            "XXXXXXXX",
+            "X .    X",
+            "X  *   X",
+            "X   *  X",
+            "X  @ . X",
+            "XXXXXXXX",
+        ]);
+        assert_eq!(None, DeadlockAnalyzer::new(&map).analyse(&map));
+    }
+
+    #[test]
+    fn test_empty_level() {
+        let map = Map::new();
+        assert_eq!(None, DeadlockAnalyzer::new(&map).analyse(&map));
+        let map = parse(&["XXXX"]);
+        assert_eq!(None, DeadlockAnalyzer::new(&map).analyse(&map));
+    }
+
+    #[test]
+    fn test_closed_corral() {
+        let map = parse(&[
+            "XXXXXXX",
+            "X. .  X",
+            "X  *  X",
+            "XXX XXX",
+            "X  *  X",
+            "X  @  X",
+            "XXXXXXX",
+        ]);
+        let analyzer = DeadlockAnalyzer::new(&map);
+        assert_eq!(None, analyzer.analyse(&map));
+
+        // Pushing the lower block into the gap seals the room: the blocks now hold each other.
+        let (map, _) = movement::apply_move(&map, MoveDirection::Up);
+        assert_eq!(None, deadlock::find_deadlock(&map, analyzer.dead_squares()));
+        assert_eq!(Some(Deadlock::ClosedCorral(Position { x: 1, y: 1 })), analyzer.analyse(&map));
+        assert_eq!(Some(Deadlock::ClosedCorral(Position { x: 1, y: 1 })), analyzer.analyse_push(&map, &Position { x: 3, y: 3 }));
+    }
+}
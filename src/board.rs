@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+
+use crate::{Map, MapTile, MoveDirection, Position};
+use crate::movement;
+
+/// Push distance of a cell from which a box cannot reach the target.
+pub(crate) const UNREACHABLE: u32 = u32::MAX;
+
+/// The static part of a level with cells numbered row by row, shared by the deadlock
+/// analysis and the solver.
+#[derive(Debug, Clone)]
+pub(crate) struct Board {
+    pub(crate) width: usize,
+    pub(crate) floor: Vec<bool>,
+    pub(crate) goals: Vec<bool>,
+    /// Neighbouring cells in the order of [`MoveDirection::ALL`], `None` beyond the map bounds.
+    pub(crate) neighbours: Vec<[Option<usize>; 4]>,
+    /// Floor cells from which a lone box cannot be pushed onto any goal.
+    dead: Vec<bool>,
+}
+
+impl Board {
+    /// Builds the board for the level of `map`, ignoring its blocks.
+    pub(crate) fn new(map: &Map) -> Board {
+        let mut empty_map = map.clone();
+        empty_map.movable_blocks.clear();
+        let (width, height) = (map.width(), map.height());
+        let mut board = Board { width, floor: Vec::new(), goals: Vec::new(), neighbours: Vec::new(), dead: Vec::new() };
+        for y in 0..height {
+            for x in 0..width {
+                let position = Position { x: x as i32, y: y as i32 };
+                board.floor.push(movement::can_move_to(&empty_map, &position, &MoveDirection::Up, true));
+                board.goals.push(map.get_tile_type_for_position(&position) == MapTile::TargetZone);
+                let mut neighbours = [None; 4];
+                for (neighbour, movedir) in neighbours.iter_mut().zip(MoveDirection::ALL.iter()) {
+                    let next = movement::calc_new_position_after_movement(movedir, &position);
+                    if next.x >= 0 && next.y >= 0 && (next.x as usize) < width && (next.y as usize) < height {
+                        *neighbour = Some(board.index(&next));
+                    }
+                }
+                board.neighbours.push(neighbours);
+            }
+        }
+        let distances = board.push_distances((0..board.len()).filter(|cell| board.goals[*cell]));
+        board.dead = (0..board.len()).map(|cell| board.floor[cell] && distances[cell] == UNREACHABLE).collect();
+        board
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.floor.len()
+    }
+
+    pub(crate) fn index(&self, position: &Position) -> usize {
+        position.y as usize * self.width + position.x as usize
+    }
+
+    pub(crate) fn position(&self, cell: usize) -> Position {
+        Position { x: (cell % self.width) as i32, y: (cell / self.width) as i32 }
+    }
+
+    /// The cell next to `cell` into `movedir`, `None` beyond the map bounds.
+    pub(crate) fn step(&self, cell: usize, movedir: MoveDirection) -> Option<usize> {
+        let dir_idx = match movedir {
+            MoveDirection::Up => 0,
+            MoveDirection::Down => 1,
+            MoveDirection::Left => 2,
+            MoveDirection::Right => 3,
+        };
+        self.neighbours[cell][dir_idx]
+    }
+
+    pub(crate) fn is_dead(&self, cell: usize) -> bool {
+        self.dead[cell]
+    }
+
+    /// For every cell the number of pushes a lone box needs from it to the nearest of `targets`,
+    /// [`UNREACHABLE`] if it cannot reach any of them.
+    pub(crate) fn push_distances(&self, targets: impl IntoIterator<Item = usize>) -> Vec<u32> {
+        let mut distances = vec![UNREACHABLE; self.len()];
+        let mut queue = VecDeque::new();
+        for target in targets {
+            distances[target] = 0;
+            queue.push_back(target);
+        }
+        // Walk backwards: a box reaches `cell` from `previous` if the player can stand behind it.
+        while let Some(cell) = queue.pop_front() {
+            for movedir in MoveDirection::ALL.iter() {
+                let previous = self.step(cell, movedir.opposite());
+                let player = previous.and_then(|previous| self.step(previous, movedir.opposite()));
+                if let (Some(previous), Some(player)) = (previous, player) {
+                    if self.floor[previous] && self.floor[player] && distances[previous] == UNREACHABLE {
+                        distances[previous] = distances[cell] + 1;
+                        queue.push_back(previous);
+                    }
+                }
+            }
+        }
+        distances
+    }
+
+    /// For every cell the number of pushes a lone box on `source` needs to reach it, [`UNREACHABLE`]
+    /// if it cannot get there.
+    pub(crate) fn push_distances_from(&self, source: usize) -> Vec<u32> {
+        let mut distances = vec![UNREACHABLE; self.len()];
+        let mut queue = VecDeque::new();
+        distances[source] = 0;
+        queue.push_back(source);
+        while let Some(cell) = queue.pop_front() {
+            for movedir in MoveDirection::ALL.iter() {
+                let player = self.step(cell, movedir.opposite());
+                let next = self.step(cell, *movedir);
+                if let (Some(player), Some(next)) = (player, next) {
+                    if self.floor[player] && self.floor[next] && distances[next] == UNREACHABLE {
+                        distances[next] = distances[cell] + 1;
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        distances
+    }
+
+    /// Marks the cells in `boxes`.
+    pub(crate) fn box_mask(&self, boxes: impl IntoIterator<Item = usize>) -> Vec<bool> {
+        let mut mask = vec![false; self.len()];
+        for cell in boxes {
+            mask[cell] = true;
+        }
+        mask
+    }
+
+    /// Cells of the blocks of the live map.
+    pub(crate) fn block_cells<'a>(&'a self, map: &'a Map) -> impl Iterator<Item = usize> + 'a {
+        map.movable_blocks.iter().map(move |block| self.index(&block.position))
+    }
+
+    /// Cells the player can walk to from `start` without pushing, none if `start` is off the board.
+    pub(crate) fn reachable(&self, box_mask: &[bool], start: usize) -> Vec<bool> {
+        let mut reachable = vec![false; self.len()];
+        if start >= self.len() {
+            return reachable;
+        }
+        let mut stack = vec![start];
+        reachable[start] = true;
+        while let Some(cell) = stack.pop() {
+            for next in self.neighbours[cell].iter().flatten() {
+                if self.floor[*next] && !box_mask[*next] && !reachable[*next] {
+                    reachable[*next] = true;
+                    stack.push(*next);
+                }
+            }
+        }
+        reachable
+    }
+}
@@ -0,0 +1,166 @@
+use std::fmt;
+
+use crate::{Map, MapTile, Position};
+use crate::board::Board;
+
+/// Cells of a level from which a block cannot be pushed onto any target zone,
+/// even if there were no other blocks.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DeadSquares {
+    width: usize,
+    dead: Vec<bool>,
+}
+
+impl DeadSquares {
+    /// Computes the dead squares of `map`.
+    pub fn new(map: &Map) -> DeadSquares {
+        DeadSquares::from_board(&Board::new(map))
+    }
+
+    /// Takes the dead squares over from `board`, see [`Board::push_distances`].
+    pub(crate) fn from_board(board: &Board) -> DeadSquares {
+        DeadSquares { width: board.width, dead: (0..board.len()).map(|cell| board.is_dead(cell)).collect() }
+    }
+
+    /// Returns whether `position` is a floor cell from which no block can reach a target zone.
+    pub fn is_dead(&self, position: &Position) -> bool {
+        position.x >= 0 && position.y >= 0 && (position.x as usize) < self.width
+            && self.dead.get(position.y as usize * self.width + position.x as usize).copied().unwrap_or(false)
+    }
+
+    /// All dead squares, row by row.
+    pub fn positions(&self) -> Vec<Position> {
+        self.dead.iter().enumerate()
+            .filter(|(_, dead)| **dead)
+            .map(|(idx, _)| Position { x: (idx % self.width) as i32, y: (idx / self.width) as i32 })
+            .collect()
+    }
+}
+
+/// A block position which makes the level unsolvable.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Deadlock {
+    /// The block at the given position stands on a dead square, e.g. in a corner.
+    DeadSquare(Position),
+    /// The 2x2 square with the given top-left corner is filled with walls and blocks,
+    /// and at least one of the blocks is not on a target zone.
+    FrozenSquare(Position),
+    /// The block at the given position belongs to a group of blocks holding each other in place,
+    /// not all of them on target zones.
+    FrozenBlocks(Position),
+    /// The area the player cannot enter around the given position is sealed off by blocks
+    /// which can never be pushed, and still has work left.
+    ClosedCorral(Position),
+}
+
+impl fmt::Display for Deadlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Deadlock::DeadSquare(position) =>
+                write!(f, "the block in column {}, row {} can no longer reach a target zone", position.x + 1, position.y + 1),
+            Deadlock::FrozenSquare(position) =>
+                write!(f, "the blocks near column {}, row {} are stuck against each other", position.x + 1, position.y + 1),
+            Deadlock::FrozenBlocks(position) =>
+                write!(f, "the block in column {}, row {} is frozen together with its neighbours", position.x + 1, position.y + 1),
+            Deadlock::ClosedCorral(position) =>
+                write!(f, "the area around column {}, row {} is closed off by blocks which can no longer move", position.x + 1, position.y + 1),
+        }
+    }
+}
+
+/// Returns the top-left corner of a 2x2 square containing `position` which is filled with
+/// walls and blocks of the live map and has a block off the target zones, if there is one.
+pub fn frozen_square(map: &Map, position: &Position) -> Option<Position> {
+    let is_wall = |position: &Position| matches!(map.get_tile_type_for_position(position), MapTile::Wall | MapTile::Outside);
+    let is_block = |position: &Position| map.is_movable_block_at(position);
+    for (dx, dy) in [(0, 0), (-1, 0), (0, -1), (-1, -1)] {
+        let top_left = Position { x: position.x + dx, y: position.y + dy };
+        let square = [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(x, y)| Position { x: top_left.x + x, y: top_left.y + y });
+        let is_filled = square.iter().all(|cell| is_wall(cell) || is_block(cell));
+        let has_loose_block = square.iter()
+            .any(|cell| is_block(cell) && map.get_tile_type_for_position(cell) != MapTile::TargetZone);
+        if is_filled && has_loose_block {
+            return Some(top_left);
+        }
+    }
+    None
+}
+
+/// Checks the block at `position` of the live map for a dead square or a frozen 2x2 square.
+pub fn check_block(map: &Map, dead_squares: &DeadSquares, position: &Position) -> Option<Deadlock> {
+    if dead_squares.is_dead(position) {
+        return Some(Deadlock::DeadSquare(*position));
+    }
+    frozen_square(map, position).map(Deadlock::FrozenSquare)
+}
+
+/// Checks all blocks of the live map, see [`check_block`].
+pub fn find_deadlock(map: &Map, dead_squares: &DeadSquares) -> Option<Deadlock> {
+    map.movable_blocks.iter().find_map(|block| check_block(map, dead_squares, &block.position))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    #[test]
+    fn test_dead_squares() {
+        let map = parse(&[
+            "XXXXXX",
+            "X    X",
+            "X @* X",
+            "X  . X",
+            "XXXXXX",
+        ]);
+        let dead_squares = DeadSquares::new(&map);
+        let dead: Vec<(i32, i32)> = dead_squares.positions().iter().map(|position| (position.x, position.y)).collect();
+        assert_eq!(vec![(1, 1), (2, 1), (3, 1), (4, 1), (1, 2), (4, 2), (1, 3), (4, 3)], dead);
+        assert!(dead_squares.is_dead(&Position { x: 1, y: 1 }));
+        assert!(!dead_squares.is_dead(&Position { x: 3, y: 3 }));
+        assert!(!dead_squares.is_dead(&Position { x: 0, y: 0 }));
+        assert!(!dead_squares.is_dead(&Position { x: -1, y: 2 }));
+        assert!(!dead_squares.is_dead(&Position { x: 9, y: 9 }));
+    }
+
+    #[test]
+    fn test_corner() {
+        let map = parse(&["XXXXXX", "X*  .X", "X   @X", "XXXXXX"]);
+        let dead_squares = DeadSquares::new(&map);
+        assert_eq!(Some(Deadlock::DeadSquare(Position { x: 1, y: 1 })), find_deadlock(&map, &dead_squares));
+    }
+
+    #[test]
+    fn test_frozen_square() {
+        let map = parse(&[
+            "XXXXXXX",
+            "X.    X",
+            "X ** .X",
+            "X **  X",
+            "X. @ .X",
+            "XXXXXXX",
+        ]);
+        let dead_squares = DeadSquares::new(&map);
+        assert_eq!(Some(Position { x: 2, y: 2 }), frozen_square(&map, &Position { x: 2, y: 2 }));
+        assert_eq!(Some(Deadlock::FrozenSquare(Position { x: 2, y: 2 })), check_block(&map, &dead_squares, &Position { x: 3, y: 3 }));
+    }
+
+    #[test]
+    fn test_frozen_square_against_wall() {
+        let map = parse(&["XXXXXX", "X ** X", "X .. X", "X @  X", "XXXXXX"]);
+        assert_eq!(Some(Position { x: 2, y: 0 }), frozen_square(&map, &Position { x: 2, y: 1 }));
+        let map = parse(&["XXXXXX", "X *  X", "X .  X", "X @  X", "XXXXXX"]);
+        assert_eq!(None, frozen_square(&map, &Position { x: 2, y: 1 }));
+    }
+
+    #[test]
+    fn test_square_on_targets_is_no_deadlock() {
+        let map = parse(&["XXXXXX", "X && X", "X  @ X", "XXXXXX"]);
+        assert_eq!(None, find_deadlock(&map, &DeadSquares::new(&map)));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!("the block in column 2, row 3 can no longer reach a target zone", Deadlock::DeadSquare(Position { x: 1, y: 2 }).to_string());
+    }
+}
@@ -1,13 +1,18 @@
-use crate::map::{Map, MapTile};
+use sokoban::{LevelCollection, MapCell, MoveDirection, Position};
+use sokoban::movement;
+use sokoban::session::{LevelScore, LevelSession};
+
+use crate::game::LevelStatus;
 use std::io::{Stdout, stdout, Write};
 
 use crossterm::{
-     queue, style::Print, terminal, cursor
+     queue, style::{Print, PrintStyledContent, Stylize}, terminal, cursor
 };
 
 pub trait Draw {
     fn setup(&self);
-    fn draw(&mut self, map : &Map);
+    fn draw(&mut self, session : &LevelSession, status: &LevelStatus, collection: Option<&LevelCollection>);
+    fn draw_level_complete(&mut self, session : &LevelSession, status: &LevelStatus, previous_best: Option<&LevelScore>, has_next_map: bool);
     fn teardown(&self);
 }
 
@@ -21,11 +26,85 @@ impl TerminalDrawer {
         TerminalDrawer { stdout: stdout() }
     }
 
-    fn draw_help_text(&mut self, map : &Map) {
+    fn draw_help_text(&mut self, session : &LevelSession, status: &LevelStatus, collection: Option<&LevelCollection>) {
        let string_to_print = format!(
-            "Map {}\r\nq - quit, r - reset, n - next map, p - previous map\r\n",
-            map.id);
+            "{}\r\n{}\r\nq - quit, r - reset, u - undo, ctrl-r - redo, h - hint, n - next map, p - previous map\r\n",
+            Self::level_heading(session, collection).join("\r\n"), session.lurd());
        queue!(self.stdout, Print(string_to_print)).unwrap();
+       if let Some(message) = &status.message {
+           queue!(self.stdout, Print(format!("{}\r\n", message))).unwrap();
+       }
+       if let Some(warning) = &status.warning {
+           queue!(self.stdout, Print(format!("Warning: {}\r\n", warning))).unwrap();
+       }
+       if let Some(hint_message) = &status.hint_message {
+           queue!(self.stdout, Print(format!("{}\r\n", hint_message))).unwrap();
+       }
+    }
+
+    fn level_heading(session : &LevelSession, collection: Option<&LevelCollection>) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(collection) = collection {
+            let author = collection.info.author.as_ref().map(|author| format!(" by {}", author)).unwrap_or_default();
+            lines.push(format!("{}{}", collection.display_title(), author));
+        }
+        let info = &session.map.info;
+        let mut map_line = format!("Map {}", session.map.display_number());
+        if let Some(title) = &info.title {
+            map_line += &format!(": {}", title);
+        }
+        if let Some(author) = &info.author {
+            map_line += &format!(" by {}", author);
+        }
+        if let Some(difficulty) = &info.difficulty {
+            map_line += &format!(" [{}]", difficulty);
+        }
+        lines.push(format!("{}   Moves: {}   Pushes: {}", map_line, session.nof_moves(), session.nof_pushes()));
+        if let Some(comment) = &info.comment {
+            lines.extend(comment.lines().map(String::from));
+        }
+        lines
+    }
+
+    fn format_score(score: &LevelScore) -> String {
+        let seconds = score.time.as_secs();
+        format!("{} moves, {} pushes, {}:{:02}", score.moves, score.pushes, seconds / 60, seconds % 60)
+    }
+
+    fn symbol_for_push(movedir: MoveDirection) -> &'static str {
+        match movedir {
+            MoveDirection::Up => "^",
+            MoveDirection::Down => "v",
+            MoveDirection::Left => "<",
+            MoveDirection::Right => ">",
+        }
+    }
+
+    fn draw_hint(&mut self, session : &LevelSession, status: &LevelStatus) {
+        if let Some(push) = &status.hint {
+            let block_symbol = Self::symbol_for_cell(session.map.get_cell_for_position(&push.block));
+            let target = movement::calc_new_position_after_movement(&push.direction, &push.block);
+            queue!(
+                self.stdout,
+                cursor::MoveTo((push.block.x + 1) as u16, (push.block.y + 1) as u16),
+                PrintStyledContent(block_symbol.reverse()),
+                cursor::MoveTo((target.x + 1) as u16, (target.y + 1) as u16),
+                PrintStyledContent(Self::symbol_for_push(push.direction).bold())
+            )
+            .unwrap();
+        }
+    }
+
+    fn symbol_for_cell(cell: MapCell) -> &'static str {
+        match cell {
+            MapCell::Wall => "X",
+            MapCell::TargetZone => ".",
+            MapCell::Block => "*",
+            MapCell::BlockOnTargetZone => "&",
+            MapCell::Player => "@",
+            MapCell::PlayerOnTargetZone => "+",
+            MapCell::Space | MapCell::Outside => " ",
+        }
     }
 }
 
@@ -34,56 +113,59 @@ impl Draw for TerminalDrawer {
         terminal::enable_raw_mode().unwrap();        
     }
     
-	fn draw(&mut self, map : &Map) {
-		
+	fn draw(&mut self, session : &LevelSession, status: &LevelStatus, collection: Option<&LevelCollection>) {
+        let map = &session.map;
        queue!(self.stdout, terminal::Clear(terminal::ClearType::All)).unwrap();
-        let mut map_dim = 0;
-        for y in 0..map.map.len() {
-            let mut current_line = String::new();
-            for x in 0..map.map[y].len() {
-                match map.map[y][x] {
-                    MapTile::Wall => current_line += "X",
-                    MapTile::TargetZone => current_line += ".",
-                    _ => current_line += " ",
-                }
-            }
+        for y in 0..map.height() {
+            let current_line: String = (0..map.width())
+                .map(|x| Self::symbol_for_cell(map.get_cell_for_position(&Position { x: x as i32, y: y as i32 })))
+                .collect();
             queue!(
                 self.stdout,
                 cursor::MoveTo(1,(y+1) as u16),
                 Print(&current_line)
             )
             .unwrap();
-            if current_line.trim().is_empty() {
-                map_dim = y;
-                break;
-            }
-        }
-        queue!(
-            self.stdout,
-            cursor::MoveTo(
-                (map.player_position.x + 1) as u16,
-                (map.player_position.y + 1) as u16
-            ),
-            Print("@".to_string())
-        )
-        .unwrap();
-        for block in map.movable_blocks.iter() {
-            queue!(
-                self.stdout,
-                cursor::MoveTo((block.position.x + 1) as u16, (block.position.y + 1) as u16),
-                Print("*".to_string())
-            )
-            .unwrap();
         }
+        self.draw_hint(session, status);
        queue!(
             self.stdout,
-            cursor::MoveTo(0, (map_dim + 2) as u16))
+            cursor::MoveTo(0, (map.height() + 2) as u16))
         .unwrap();
 
-        self.draw_help_text(map);
+        self.draw_help_text(session, status, collection);
         self.stdout.flush().unwrap();
 	}
 
+    fn draw_level_complete(&mut self, session : &LevelSession, status: &LevelStatus, previous_best: Option<&LevelScore>, has_next_map: bool) {
+        let mut lines = vec![
+            format!("Map {} complete!", session.map.display_number()),
+            String::new(),
+            format!("Your result:   {}", Self::format_score(&session.score())),
+        ];
+        match previous_best {
+            Some(best) if !session.score().is_better_than(best) => lines.push(format!("Personal best: {}", Self::format_score(best))),
+            Some(best) => lines.push(format!("New personal best! Previous: {}", Self::format_score(best))),
+            None => lines.push(String::from("New personal best!")),
+        }
+        lines.push(String::new());
+        if has_next_map {
+            lines.push(String::from("n - continue, r - retry, v - replay, q - quit"));
+        } else {
+            lines.push(String::from("This was the last map."));
+            lines.push(String::from("r - retry, v - replay, p - previous map, q - quit"));
+        }
+        if let Some(message) = &status.message {
+            lines.push(message.clone());
+        }
+
+        queue!(self.stdout, terminal::Clear(terminal::ClearType::All)).unwrap();
+        for (y, line) in lines.iter().enumerate() {
+            queue!(self.stdout, cursor::MoveTo(1, (y + 1) as u16), Print(line)).unwrap();
+        }
+        self.stdout.flush().unwrap();
+    }
+
     fn teardown(&self) {
         terminal::disable_raw_mode().unwrap();        
     }
@@ -92,9 +174,25 @@ impl Draw for TerminalDrawer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use sokoban::{CollectionInfo, LevelInfo, Map};
 
     #[test]
     fn test_drawing_can_be_instatiated() {
         let _drawing_module = TerminalDrawer::new();
     }
+
+    #[test]
+    fn test_level_heading() {
+        let mut map = Map::new();
+        map.parse_map_block(&["X@*.X"]);
+        let session = LevelSession::new(map.clone());
+        assert_eq!(vec![String::from("Map 1   Moves: 0   Pushes: 0")], TerminalDrawer::level_heading(&session, None));
+
+        map.info = LevelInfo { title: Some(String::from("Start")), author: Some(String::from("Max")), difficulty: Some(String::from("easy")),
+            comment: Some(String::from("warm\nup")), ..LevelInfo::default() };
+        let collection = LevelCollection { name: String::from("demo"), info: CollectionInfo { author: Some(String::from("Jo")), ..CollectionInfo::default() },
+            map_ids: 0..1 };
+        assert_eq!(vec!["demo by Jo", "Map 1: Start by Max [easy]   Moves: 0   Pushes: 0", "warm", "up"],
+            TerminalDrawer::level_heading(&LevelSession::new(map), Some(&collection)));
+    }
 }
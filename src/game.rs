@@ -1,96 +1,311 @@
+use std::collections::HashMap;
 use std::io;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
 
-use crate::input::{GameCommand, UserInputProvider};
-use crate::{Map, MapTile, MapManager,MoveDirection, DefaultMapContentProvider, Position};
+use sokoban::{Map, MapManager,MoveDirection, DefaultMapContentProvider, EmbeddedMapContentProvider, FileMapContentProvider,
+    StdinMapContentProvider, StringMapContentProvider};
+use sokoban::analysis::DeadlockAnalyzer;
+use sokoban::deadlock::Deadlock;
+use sokoban::movement::MoveOutcome;
+use sokoban::session::{LevelScore, LevelSession};
+use sokoban::solution;
+use sokoban::solver::{self, Push, SearchLimits, SolverOutcome};
+use sokoban::sources::LevelSource;
+
+use crate::input::{GameCommand, InputAction, UserInputProvider};
 use crate::{PlatformSpecific, Draw};
-use crate::movement;
+
+const REPLAY_STEP_DELAY: Duration = Duration::from_millis(150);
+const HINT_TIME_LIMIT: Duration = Duration::from_secs(10);
+const HINT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const UNSOLVABLE_MESSAGE: &str = "This position can no longer be solved, undo some moves or press r to restart.";
+
+/// Text and highlights shown along with the map of the current level.
+#[derive(Debug, Default)]
+pub struct LevelStatus {
+    /// Message to show to the player along with the map.
+    pub message: Option<String>,
+    /// Warning about the current position, e.g. a deadlock, shown below the message.
+    pub warning: Option<String>,
+    /// Push suggested to the player, highlighted on the map.
+    pub hint: Option<Push>,
+    /// Answer to the last hint request, shown below the warning until the next command or move.
+    pub hint_message: Option<String>,
+}
+
+/// A hint search running on its own thread, so the player can keep playing meanwhile.
+struct HintSearch {
+    outcome: Receiver<SolverOutcome>,
+    stop: Arc<AtomicBool>,
+    max_time: Duration,
+}
+
 pub struct Game {
     map_manager: MapManager,
     current_map_id: u32,
+    session: LevelSession,
+    status: LevelStatus,
+    hint_search: Option<HintSearch>,
+    personal_bests: HashMap<u32, LevelScore>,
+    previous_best: Option<LevelScore>,
+    deadlock_analyzer: DeadlockAnalyzer,
 }
 
 impl Game {
 
     pub fn new() -> Game {
-        Game {map_manager: MapManager { maps: Vec::new() }, current_map_id: 0 }    
+        Game {map_manager: MapManager::new(), current_map_id: 0, session: LevelSession::new(Map::new()),
+            status: LevelStatus::default(), hint_search: None, personal_bests: HashMap::new(),
+            previous_best: None, deadlock_analyzer: DeadlockAnalyzer::new(&Map::new()) }
     }
     
-    pub fn init(&mut self, platform: &PlatformSpecific) -> Result<(), io::Error> {
-        self.map_manager.read_maps(DefaultMapContentProvider {})?;
+    pub fn init(&mut self, platform: &PlatformSpecific, level_sources: &[LevelSource]) -> Result<(), io::Error> {
+        self.read_collections(level_sources)?;
+        self.switch_to_map(0);
         platform.renderer.setup();
         Ok(())
     }
 
+    fn read_collections(&mut self, level_sources: &[LevelSource]) -> Result<(), io::Error> {
+        if level_sources.is_empty() && self.map_manager.read_maps(DefaultMapContentProvider {}).is_err() {
+            self.map_manager.read_maps(EmbeddedMapContentProvider {})?;
+        }
+        for source in level_sources {
+            let result = match source {
+                LevelSource::File(path) => self.map_manager.read_maps(FileMapContentProvider { path: path.clone() }),
+                LevelSource::Stdin => self.map_manager.read_maps(StdinMapContentProvider {}),
+                LevelSource::Text(map_contents) => self.map_manager.read_maps(StringMapContentProvider { map_contents: map_contents.clone() }),
+            };
+            result.map_err(|error| io::Error::new(error.kind(), format!("{}: {}", source, error)))?;
+        }
+        if self.map_manager.maps.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the configured level sources contain no levels"));
+        }
+        Ok(())
+    }
+
+    pub fn verify_solutions(&mut self, solution_file: &Path, level_sources: &[LevelSource]) -> Result<Vec<String>, io::Error> {
+        self.read_collections(level_sources)?;
+        let solutions = solution::parse_solutions(&fs::read_to_string(solution_file)?, &self.map_manager.maps);
+        Ok(solution::verify_solutions(&self.map_manager.maps, &solutions).into_iter()
+            .map(|(level_idx, report)| format!("Level {}: {}", self.map_manager.maps[level_idx].display_number(), report))
+            .collect())
+    }
+
     fn get_current_map(&self) -> Map {
         self.map_manager.maps[self.current_map_id as usize].clone()
     }
 
+    fn switch_to_map(&mut self, map_id: u32) {
+        self.current_map_id = map_id;
+        self.session.switch_level(self.get_current_map());
+        self.status = LevelStatus::default();
+        self.deadlock_analyzer = DeadlockAnalyzer::new(&self.session.map);
+        self.update_deadlock_warning();
+        self.status.message = self.map_manager.validation_errors_for_map(map_id)
+            .next()
+            .map(|error| format!("Invalid level: {}", error));
+    }
+
     pub fn main_loop(&mut self, platform: &mut PlatformSpecific) {
         while let Some(cmd) = self.input_loop(platform) {
             match cmd {
                 GameCommand::Quit => break,
-                GameCommand::NextMap => {
-                    if !self.map_manager.maps.is_empty() && self.current_map_id + 1 < self.map_manager.maps.len() as u32 { 
-                        self.current_map_id += 1
-                    }}
-                GameCommand::PreviousMap => {
-                        if self.current_map_id > 0 {
-                            self.current_map_id -= 1;
-                }}
-                _ => ()
+                GameCommand::Replay => {
+                    self.clear_hint();
+                    self.replay(platform);
+                }
+                _ => self.handle_command(cmd),
             }
         }
     }
 
-    fn render(&self, drawer : &mut Box<(dyn Draw)>, map: &Map) {
-        drawer.draw(map);
+    /// Runs a command which does not need the platform, dropping the hint of the previous position.
+    fn handle_command(&mut self, cmd: GameCommand) {
+        self.clear_hint();
+        match cmd {
+            GameCommand::NextMap => self.next_map(),
+            GameCommand::PreviousMap if self.current_map_id > 0 => {
+                self.switch_to_map(self.current_map_id - 1);
+            }
+            GameCommand::Reset => {
+                self.session.restart();
+                self.update_deadlock_warning();
+            }
+            GameCommand::Undo => {
+                self.session.undo();
+                self.update_deadlock_warning();
+            }
+            GameCommand::Redo => {
+                self.redo();
+                self.update_deadlock_warning();
+            }
+            GameCommand::Hint => self.show_hint(HINT_TIME_LIMIT),
+            _ => ()
+        }
+    }
+
+    fn has_next_map(&self) -> bool {
+        self.current_map_id + 1 < self.map_manager.maps.len() as u32
+    }
+
+    fn next_map(&mut self) {
+        if self.has_next_map() {
+            self.switch_to_map(self.current_map_id + 1);
+        } else {
+            self.status.message = Some(String::from("There is no next map, this is the last one."));
+        }
     }
 
-    fn input_loop(&self, platform: &mut PlatformSpecific) -> Option<GameCommand> {
-        let mut current_map = self.get_current_map();
-        self.render(&mut platform.renderer, &current_map);
-        let mut user_input = platform.input_provider.get_user_input();
+    fn replay(&mut self, platform: &mut PlatformSpecific) {
+        let mut replay = self.session.clone();
+        let status = LevelStatus::default();
+        while replay.undo() {}
+        let collection = self.map_manager.collection_for_map(self.current_map_id);
+        platform.renderer.draw(&replay, &status, collection);
+        while replay.redo() {
+            thread::sleep(REPLAY_STEP_DELAY);
+            platform.renderer.draw(&replay, &status, collection);
+        }
+        thread::sleep(REPLAY_STEP_DELAY);
+    }
+
+    fn render(&self, drawer : &mut Box<dyn Draw>) {
+        drawer.draw(&self.session, &self.status, self.map_manager.collection_for_map(self.current_map_id));
+    }
+
+    fn input_loop(&mut self, platform: &mut PlatformSpecific) -> Option<GameCommand> {
+        if self.session.is_won() {
+            return self.level_complete_loop(platform);
+        }
+        self.render(&mut platform.renderer);
+        let mut user_input = self.next_input(platform);
         while let Some(movedir) = user_input.movement_command {
-            self.handle_movement(&mut current_map, movedir);
-            self.render(&mut platform.renderer, &current_map);
-            if self.check_has_won(&current_map) {
-                return Some(GameCommand::NextMap);
+            self.handle_movement(movedir);
+            self.render(&mut platform.renderer);
+            if self.session.is_won() {
+                return self.level_complete_loop(platform);
             }
-            user_input = platform.input_provider.get_user_input();
+            user_input = self.next_input(platform);
         }
 
         user_input.game_command
     }
 
-    fn handle_movement(&self, current_map: &mut Map, movedir: MoveDirection) {
-        let new_pos = movement::calc_new_position_after_movement(&movedir, &current_map.player_position);
-        if movement::can_move_to(&current_map, &new_pos, &movedir, false) {
-            if let Some(block) = current_map.get_movable_block_at(&new_pos) {
-                let new_pos_block = movement::calc_new_position_after_movement(&movedir, &new_pos);
-                block.move_to(&movedir);
-                self.calc_nof_blocks_in_target_position(current_map, &new_pos, &new_pos_block);
+    /// Waits for the next input, showing the outcome of a running hint search as soon as it is there.
+    fn next_input(&mut self, platform: &mut PlatformSpecific) -> InputAction {
+        while self.hint_search.is_some() && !platform.input_provider.has_user_input(HINT_POLL_INTERVAL) {
+            if self.receive_hint() {
+                self.render(&mut platform.renderer);
+            }
+        }
+        platform.input_provider.get_user_input()
+    }
+
+    fn level_complete_loop(&mut self, platform: &mut PlatformSpecific) -> Option<GameCommand> {
+        platform.renderer.draw_level_complete(&self.session, &self.status, self.previous_best.as_ref(), self.has_next_map());
+        loop {
+            match platform.input_provider.get_user_input().game_command {
+                Some(GameCommand::Undo) | Some(GameCommand::Redo) | None => (),
+                cmd => return cmd,
+            }
+        }
+    }
+
+    /// Starts the search for a hint on the current position, see [`Game::receive_hint`] for its outcome.
+    fn show_hint(&mut self, max_time: Duration) {
+        if self.deadlock_analyzer.analyse(&self.session.map).is_some() {
+            self.status.hint_message = Some(String::from(UNSOLVABLE_MESSAGE));
+            return;
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let limits = SearchLimits { max_nodes: None, max_time: Some(max_time), stop: Some(stop.clone()) };
+        let map = self.session.map.clone();
+        let (sender, outcome) = mpsc::channel();
+        thread::spawn(move || {
+            // The game no longer waits for the outcome if the search was stopped.
+            let _ = sender.send(solver::solve_backward(&map, &limits).outcome);
+        });
+        self.hint_search = Some(HintSearch { outcome, stop, max_time });
+        self.status.hint_message = Some(String::from("Looking for a hint..."));
+    }
+
+    /// Shows the outcome of the running hint search if it is done, true if the status changed.
+    fn receive_hint(&mut self) -> bool {
+        let search = match &self.hint_search {
+            Some(search) => search,
+            None => return false,
+        };
+        let outcome = match search.outcome.try_recv() {
+            Ok(outcome) => outcome,
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Disconnected) => SolverOutcome::LimitReached,
+        };
+        let max_time = search.max_time;
+        self.hint_search = None;
+        self.status.hint_message = match outcome {
+            SolverOutcome::Solved(solution) => {
+                self.status.hint = solution.pushes.first().copied();
+                self.status.hint.map(|push| format!("Hint: push the highlighted block in column {}, row {} {}.",
+                    push.block.x + 1, push.block.y + 1, direction_name(push.direction)))
             }
-            current_map.player_position = new_pos;
+            SolverOutcome::Unsolvable => Some(String::from(UNSOLVABLE_MESSAGE)),
+            SolverOutcome::LimitReached => Some(format!(
+                "No hint found within {} seconds, the solver does not cope with this many blocks yet.", max_time.as_secs())),
+        };
+        true
+    }
+
+    /// Drops the hint and stops the search for one.
+    fn clear_hint(&mut self) {
+        if let Some(search) = self.hint_search.take() {
+            search.stop.store(true, Ordering::Relaxed);
         }
+        self.status.hint = None;
+        self.status.hint_message = None;
     }
 
-    fn calc_nof_blocks_in_target_position(&self, map: &mut Map, old_position: &Position, new_position: &Position) {
-        if map.get_tile_type_for_position(&new_position) == MapTile::TargetZone && map.get_tile_type_for_position(&old_position) != MapTile::TargetZone
-        {
-            map.movable_blocks_in_final_position += 1;
+    fn handle_movement(&mut self, movedir: MoveDirection) {
+        self.clear_hint();
+        match self.session.apply_move(movedir) {
+            MoveOutcome::Solved { .. } => self.update_personal_best(),
+            MoveOutcome::Pushed { to, .. } => {
+                let deadlock = self.deadlock_analyzer.analyse_push(&self.session.map, &to);
+                self.show_deadlock_warning(deadlock);
+            }
+            _ => (),
         }
+    }
+
+    fn update_deadlock_warning(&mut self) {
+        let deadlock = self.deadlock_analyzer.analyse(&self.session.map);
+        self.show_deadlock_warning(deadlock);
+    }
 
-        if map.get_tile_type_for_position(&new_position) != MapTile::TargetZone && map.get_tile_type_for_position(&old_position) == MapTile::TargetZone
-        {
-            map.movable_blocks_in_final_position -= 1;
+    fn show_deadlock_warning(&mut self, deadlock: Option<Deadlock>) {
+        self.status.warning = deadlock.map(|deadlock| format!("{}, undo to get out of the deadlock", deadlock));
+    }
+
+    fn redo(&mut self) {
+        if self.session.redo() && self.session.is_won() {
+            self.update_personal_best();
         }
     }
 
-    fn check_has_won(&self, map: &Map) -> bool {       
-        if map.movable_blocks_in_final_position == map.movable_blocks.len() as u32 {
-            return true;
+    /// Records the score of the solved level, remembering the best from before this run for the level complete screen.
+    fn update_personal_best(&mut self) {
+        let score = self.session.score();
+        self.previous_best = self.personal_bests.get(&self.session.map.id).copied();
+        let best = self.personal_bests.entry(self.session.map.id).or_insert(score);
+        if score.is_better_than(best) {
+            *best = score;
         }
-        return false;
     }
 
     pub fn tear_down(&self, platform: &PlatformSpecific) {
@@ -98,70 +313,271 @@ impl Game {
     }
 }
 
+fn direction_name(movedir: MoveDirection) -> &'static str {
+    match movedir {
+        MoveDirection::Up => "up",
+        MoveDirection::Down => "down",
+        MoveDirection::Left => "left",
+        MoveDirection::Right => "right",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::map::MovableBlock;
+    use sokoban::{MapTile, Position};
+    use sokoban::session::Step;
+    use sokoban::solver::Push;
+    use sokoban::validation::{ValidationError, ValidationErrorKind};
+    use std::path::PathBuf;
     fn setup_tests() -> (Game, Map) {
         
         let mut game = Game::new(); 
-        let mut map = Map::new();
-        map.map[0][0] = MapTile::TargetZone;
+        let mut map = Map::with_size(2, 1);
+        map.map.set(0, 0, MapTile::TargetZone);
         game.map_manager.maps.push(map.clone());
         (game, map)
     }
 
+    fn game_with_level(rows: &[&str]) -> Game {
+        let mut game = Game::new();
+        let mut map = Map::new();
+        map.parse_map_block(rows);
+        game.map_manager.maps.push(map);
+        game.switch_to_map(0);
+        game
+    }
+
     #[test]
-    fn test_move_box_into_target_zone() {
-        let (game, mut map)  = setup_tests();
-        let old_position = Position {x: 1, y: 0};        
-        let new_position = Position {x: 0, y: 0};        
-        game.calc_nof_blocks_in_target_position(&mut map, &old_position, &new_position);
-        assert_eq!(1, map.movable_blocks_in_final_position);
+    fn test_read_collections() {
+        let mut game = Game::new();
+        let maps_file = LevelSource::File(PathBuf::from("data/maps/maps.txt"));
+        game.read_collections(&[maps_file.clone(), maps_file]).unwrap();
+        assert_eq!(120, game.map_manager.maps.len());
+        assert_eq!(2, game.map_manager.collections.len());
     }
 
-    
     #[test]
-    fn test_move_box_out_of_target_zone() {
-        let (game, mut map)  = setup_tests();
-        let old_position = Position {x: 0, y: 0};        
-        let new_position = Position {x: 1, y: 0};        
-        map.movable_blocks_in_final_position = 1;
-        game.calc_nof_blocks_in_target_position(&mut map, &old_position, &new_position);
-        assert_eq!(0, map.movable_blocks_in_final_position);
+    fn test_read_default_collection() {
+        let mut game = Game::new();
+        game.read_collections(&[]).unwrap();
+        assert_eq!(60, game.map_manager.maps.len());
+        assert_eq!(1, game.map_manager.collections.len());
     }
 
     #[test]
-    fn test_move_box_in_target_zone() {       
-        let (game, mut map)  = setup_tests();
-        let old_position = Position {x: 0, y: 0};        
-        let new_position = Position {x: 1, y: 0};        
-        map.movable_blocks_in_final_position = 1;
-        map.map[0][1] = MapTile::TargetZone;
-        game.calc_nof_blocks_in_target_position(&mut map, &old_position, &new_position);
-        assert_eq!(1, map.movable_blocks_in_final_position);
+    fn test_read_collections_errors() {
+        let mut game = Game::new();
+        let error = game.read_collections(&[LevelSource::File(PathBuf::from("missing.txt"))]).unwrap_err();
+        assert!(error.to_string().starts_with("missing.txt: "));
+        assert!(game.read_collections(&[LevelSource::File(PathBuf::from("README.md"))]).is_err());
+        let error = game.read_collections(&[LevelSource::Text(String::from("3#|#@)#|3#"))]).unwrap_err();
+        assert!(error.to_string().starts_with("level string: "));
     }
 
     #[test]
-    fn test_has_won() {       
-        let (game, mut map)  = setup_tests();
-        map.movable_blocks.push(MovableBlock { position: Position {x: 0, y: 0} });
-        map.movable_blocks_in_final_position = 1;
-        assert!(game.check_has_won(&map));
+    fn test_read_level_string() {
+        let mut game = Game::new();
+        game.read_collections(&[LevelSource::Text(String::from("#####\n#@$.#\n#####"))]).unwrap();
+        assert_eq!(1, game.map_manager.maps.len());
     }
 
     #[test]
-    fn test_has_not_won() {       
-        let (game, mut map)  = setup_tests();
-        map.movable_blocks.push(MovableBlock { position: Position {x: 0, y: 0} });
-        assert!(!game.check_has_won(&map));
+    fn test_verify_solutions() {
+        let solution_file = std::env::temp_dir().join(format!("sokoban-solutions-{}.txt", std::process::id()));
+        fs::write(&solution_file, "Level 1\nul\n\nLevel 2\nuU\n").unwrap();
+        let mut game = Game::new();
+        let reports = game.verify_solutions(&solution_file, &[]);
+        fs::remove_file(&solution_file).unwrap();
+        let reports = reports.unwrap();
+        assert_eq!(2, reports.len());
+        assert_eq!("Level 1: valid, but does not solve the level (2 moves, 0 pushes)", reports[0]);
+        assert!(reports[1].starts_with("Level 2: invalid, "));
     }
 
     #[test]
     fn test_get_current_map() {       
         let (game, _)  = setup_tests();
-        let mut equal_map = Map::new();
-        equal_map.map[0][0] = MapTile::TargetZone;
+        let mut equal_map = Map::with_size(2, 1);
+        equal_map.map.set(0, 0, MapTile::TargetZone);
         assert_eq!(equal_map, game.get_current_map());
     }
+
+    #[test]
+    fn test_handle_movement_and_reset() {
+        let (mut game, _)  = setup_tests();
+        game.switch_to_map(0);
+        game.handle_movement(MoveDirection::Right);
+        assert_eq!(Position {x: 1, y: 0}, game.session.map.player_position);
+        assert_eq!(vec![Step { direction: MoveDirection::Right, pushed_block: None }], game.session.history);
+        game.handle_command(GameCommand::Reset);
+        assert_eq!(game.get_current_map(), game.session.map);
+        assert!(game.session.history.is_empty());
+    }
+
+    #[test]
+    fn test_switch_to_invalid_map() {
+        let (mut game, _)  = setup_tests();
+        game.map_manager.validation_errors.push(ValidationError { level_idx: 0, level_number: 1, line: 3, kind: ValidationErrorKind::MissingPlayer });
+        game.switch_to_map(0);
+        assert_eq!(Some(String::from("Invalid level: level 1, line 3: level has no player")), game.status.message);
+    }
+
+    #[test]
+    fn test_next_map_on_last_map() {
+        let (mut game, _)  = setup_tests();
+        game.switch_to_map(0);
+        assert!(!game.has_next_map());
+        game.next_map();
+        assert_eq!(0, game.current_map_id);
+        assert!(game.status.message.is_some());
+    }
+
+    #[test]
+    fn test_personal_best() {
+        let mut game = game_with_level(&["X @*.X"]);
+        game.handle_movement(MoveDirection::Left);
+        game.handle_movement(MoveDirection::Right);
+        game.handle_movement(MoveDirection::Right);
+        assert_eq!(3, game.personal_bests[&0].moves);
+        assert_eq!(None, game.previous_best);
+        game.session.restart();
+        game.handle_movement(MoveDirection::Right);
+        assert_eq!(1, game.personal_bests[&0].moves);
+        game.session.restart();
+        game.handle_movement(MoveDirection::Left);
+        game.handle_movement(MoveDirection::Right);
+        game.handle_movement(MoveDirection::Right);
+        assert_eq!(1, game.personal_bests[&0].moves);
+        assert_eq!(1, game.personal_bests[&0].pushes);
+        assert_eq!(Some(1), game.previous_best.map(|best| best.moves));
+    }
+
+    #[test]
+    fn test_worse_run_shows_previous_best() {
+        let mut game = game_with_level(&["X @*.X"]);
+        game.handle_movement(MoveDirection::Right);
+        game.session.restart();
+        game.handle_movement(MoveDirection::Left);
+        game.handle_movement(MoveDirection::Right);
+        game.handle_movement(MoveDirection::Right);
+        assert_eq!(3, game.session.score().moves);
+        assert_eq!(Some(1), game.previous_best.map(|best| best.moves));
+    }
+
+    #[test]
+    fn test_handle_movement_on_open_map() {
+        let mut game = game_with_level(&["@*."]);
+        game.handle_movement(MoveDirection::Left);
+        game.handle_movement(MoveDirection::Up);
+        game.handle_movement(MoveDirection::Down);
+        assert_eq!(Position {x: 0, y: 0}, game.session.map.player_position);
+        assert!(game.session.history.is_empty());
+    }
+
+    #[test]
+    fn test_handle_movement_records_push() {
+        let mut game = game_with_level(&["X@*.X"]);
+        game.handle_movement(MoveDirection::Right);
+        assert_eq!(vec![Step { direction: MoveDirection::Right, pushed_block: Some(0) }], game.session.history);
+        assert!(game.session.is_won());
+        game.session.undo();
+        assert_eq!(game.get_current_map(), game.session.map);
+    }
+
+    #[test]
+    fn test_deadlock_warning() {
+        let mut game = game_with_level(&["XXXXXX", "X *@.X", "X    X", "XXXXXX"]);
+        assert_eq!(None, game.status.warning);
+        game.handle_movement(MoveDirection::Left);
+        assert_eq!(Some(String::from("the block in column 2, row 2 can no longer reach a target zone, undo to get out of the deadlock")),
+            game.status.warning);
+        game.handle_movement(MoveDirection::Down);
+        assert!(game.status.warning.is_some());
+        game.handle_command(GameCommand::Undo);
+        assert!(game.status.warning.is_some());
+        game.handle_command(GameCommand::Undo);
+        assert_eq!(None, game.status.warning);
+        game.handle_command(GameCommand::Redo);
+        assert!(game.status.warning.is_some());
+        game.handle_command(GameCommand::Reset);
+        assert_eq!(None, game.status.warning);
+        assert!(game.session.history.is_empty());
+    }
+
+    fn wait_for_hint(game: &mut Game) {
+        while !game.receive_hint() {
+            thread::sleep(HINT_POLL_INTERVAL);
+        }
+    }
+
+    #[test]
+    fn test_show_hint() {
+        let mut game = game_with_level(&["XXXXXXX", "X@ * .X", "XXXXXXX"]);
+        game.show_hint(HINT_TIME_LIMIT);
+        assert_eq!(Some(String::from("Looking for a hint...")), game.status.hint_message);
+        wait_for_hint(&mut game);
+        assert!(game.hint_search.is_none());
+        assert_eq!(Some(Push { block: Position { x: 3, y: 1 }, direction: MoveDirection::Right }), game.status.hint);
+        assert_eq!(Some(String::from("Hint: push the highlighted block in column 4, row 2 right.")), game.status.hint_message);
+        game.handle_movement(MoveDirection::Right);
+        assert_eq!(None, game.status.hint);
+        assert_eq!(None, game.status.hint_message);
+    }
+
+    #[test]
+    fn test_hint_message_without_hint_is_cleared() {
+        let mut game = game_with_level(&["XXXXXXX", "X@ * .X", "XXXXXXX"]);
+        game.status.message = Some(String::from("Invalid level"));
+        game.show_hint(Duration::from_secs(0));
+        wait_for_hint(&mut game);
+        assert_eq!(None, game.status.hint);
+        assert!(game.status.hint_message.as_ref().is_some_and(|message| message.starts_with("No hint found within 0 seconds")));
+        game.handle_movement(MoveDirection::Right);
+        assert_eq!(None, game.status.hint_message);
+        assert_eq!(Some(String::from("Invalid level")), game.status.message);
+    }
+
+    #[test]
+    fn test_show_hint_on_shipped_level() {
+        let mut game = Game::new();
+        game.read_collections(&[]).unwrap();
+        game.switch_to_map(1);
+        game.show_hint(HINT_TIME_LIMIT);
+        wait_for_hint(&mut game);
+        let hint = game.status.hint.expect("no hint found");
+        assert!(game.session.map.is_movable_block_at(&hint.block));
+    }
+
+    #[test]
+    fn test_move_stops_hint_search() {
+        let mut game = Game::new();
+        game.read_collections(&[]).unwrap();
+        game.switch_to_map(9);
+        game.show_hint(HINT_TIME_LIMIT);
+        let stop = game.hint_search.as_ref().unwrap().stop.clone();
+        game.handle_movement(MoveDirection::Left);
+        assert!(stop.load(Ordering::Relaxed));
+        assert!(game.hint_search.is_none());
+        assert!(!game.receive_hint());
+        assert_eq!(None, game.status.hint_message);
+    }
+
+    #[test]
+    fn test_show_hint_when_unsolvable() {
+        let mut game = game_with_level(&["XXXXXXX", "X .*@ X", "X *   X", "X    .X", "XXXXXXX"]);
+        game.handle_movement(MoveDirection::Left);
+        game.handle_movement(MoveDirection::Left);
+        game.show_hint(HINT_TIME_LIMIT);
+        assert_eq!(None, game.status.hint);
+        assert_eq!(Some(String::from(UNSOLVABLE_MESSAGE)), game.status.hint_message);
+    }
+
+    #[test]
+    fn test_closed_corral_warning() {
+        let mut game = game_with_level(&["XXXXXXX", "X. .  X", "X  *  X", "XXX XXX", "X  *  X", "X  @  X", "XXXXXXX"]);
+        game.handle_movement(MoveDirection::Up);
+        assert_eq!(Some(String::from("the area around column 2, row 2 is closed off by blocks which can no longer move, undo to get out of the deadlock")),
+            game.status.warning);
+    }
 }
@@ -0,0 +1,27 @@
+/// Descriptive information about a level.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct LevelInfo {
+    /// Title or id of the level.
+    pub title: Option<String>,
+    /// Author of the level.
+    pub author: Option<String>,
+    /// Copyright notice of the level.
+    pub copyright: Option<String>,
+    /// Free text comment on the level, may span several lines.
+    pub comment: Option<String>,
+    /// Difficulty tag of the level, e.g. `easy` or `5/10`.
+    pub difficulty: Option<String>,
+}
+
+/// Descriptive information about a level collection.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct CollectionInfo {
+    /// Title of the collection.
+    pub title: Option<String>,
+    /// Description of the collection.
+    pub description: Option<String>,
+    /// Author of the collection.
+    pub author: Option<String>,
+    /// Copyright notice of the collection.
+    pub copyright: Option<String>,
+}
@@ -1,16 +1,22 @@
+use std::time::Duration;
+
 use crossterm::{
     event::{
-        read, Event, KeyCode, KeyEvent},
+        poll, read, Event, KeyCode, KeyEvent, KeyModifiers},
     Result,
 };
-use crate::MoveDirection;
+use sokoban::MoveDirection;
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum GameCommand {
     Reset,
     Quit,
     NextMap,
-    PreviousMap
+    PreviousMap,
+    Undo,
+    Redo,
+    Replay,
+    Hint
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -21,6 +27,8 @@ pub struct InputAction {
 
 pub trait UserInputProvider {
     fn get_user_input(&mut self) -> InputAction;
+    /// Waits up to `timeout` for input, true if `get_user_input` will not block.
+    fn has_user_input(&mut self, timeout: Duration) -> bool;
 }
 
 
@@ -28,10 +36,15 @@ impl<S: UserInputProvider + ?Sized> UserInputProvider for Box<S> {
     fn get_user_input(&mut self) -> InputAction {
         (**self).get_user_input()
     }
+
+    fn has_user_input(&mut self, timeout: Duration) -> bool {
+        (**self).has_user_input(timeout)
+    }
 }
 
 pub trait TerminalInputProvider {
     fn read_key_input(&self) -> Result<Event>;
+    fn poll_key_input(&self, timeout: Duration) -> Result<bool>;
 }
 
 pub struct CrosstermInput {
@@ -41,6 +54,10 @@ impl TerminalInputProvider for CrosstermInput {
     fn read_key_input(&self) -> Result<Event> {
         read()
     }
+
+    fn poll_key_input(&self, timeout: Duration) -> Result<bool> {
+        poll(timeout)
+    }
 }
 
 pub struct TerminalInput {
@@ -62,11 +79,20 @@ impl UserInputProvider for TerminalInput {
             Event::Key(KeyEvent{code: KeyCode::Char('q') | KeyCode::Esc, ..}) => Some(GameCommand::Quit),
             Event::Key(KeyEvent{code: KeyCode::Char('n'), ..}) => Some(GameCommand::NextMap),
             Event::Key(KeyEvent{code: KeyCode::Char('p'), ..}) => Some(GameCommand::PreviousMap),
+            Event::Key(KeyEvent{code: KeyCode::Char('r'), modifiers: KeyModifiers::CONTROL}) => Some(GameCommand::Redo),
             Event::Key(KeyEvent{code: KeyCode::Char('r'), ..}) => Some(GameCommand::Reset),
+            Event::Key(KeyEvent{code: KeyCode::Char('u'), ..}) => Some(GameCommand::Undo),
+            Event::Key(KeyEvent{code: KeyCode::Char('v'), ..}) => Some(GameCommand::Replay),
+            Event::Key(KeyEvent{code: KeyCode::Char('h'), ..}) => Some(GameCommand::Hint),
             _ => None
         };
         InputAction { movement_command: movedir, game_command: cmd}
     }
+
+    fn has_user_input(&mut self, timeout: Duration) -> bool {
+        // Errors surface on the read that follows.
+        self.input_provider.poll_key_input(timeout).unwrap_or(true)
+    }
 }
 
 impl TerminalInput {
@@ -87,6 +113,10 @@ mod tests {
         fn read_key_input(&self) -> Result<Event> {
             Ok(self.key)
         }
+
+        fn poll_key_input(&self, _timeout: Duration) -> Result<bool> {
+            Ok(true)
+        }
     }
    
 
@@ -97,6 +127,7 @@ mod tests {
         };
         let mut input_provider = TerminalInput{input_provider: Box::new(fake_input_provider)};
         assert!(input_provider.get_user_input().movement_command.is_none());
+        assert!(input_provider.has_user_input(Duration::from_millis(0)));
     }
 
     macro_rules! movement_input_tests {
@@ -106,7 +137,7 @@ mod tests {
             fn $name() {
                 let (action, key) = $value;
                 let fake_input_provider = FakeInput{
-                    key: key
+                    key
                 };
                 let mut input_provider = TerminalInput{input_provider: Box::new(fake_input_provider)};
                 assert_eq!(
@@ -127,5 +158,9 @@ mod tests {
         test_p: (InputAction { movement_command: None, game_command: Some(GameCommand::PreviousMap)}, Event::Key(KeyCode::Char('p').into())),
         test_n: (InputAction { movement_command: None, game_command: Some(GameCommand::NextMap)}, Event::Key(KeyCode::Char('n').into())),
         test_r: (InputAction { movement_command: None, game_command: Some(GameCommand::Reset)}, Event::Key(KeyCode::Char('r').into())),
+        test_v: (InputAction { movement_command: None, game_command: Some(GameCommand::Replay)}, Event::Key(KeyCode::Char('v').into())),
+        test_h: (InputAction { movement_command: None, game_command: Some(GameCommand::Hint)}, Event::Key(KeyCode::Char('h').into())),
+        test_u: (InputAction { movement_command: None, game_command: Some(GameCommand::Undo)}, Event::Key(KeyCode::Char('u').into())),
+        test_ctrl_r: (InputAction { movement_command: None, game_command: Some(GameCommand::Redo)}, Event::Key(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL))),
     }
 }
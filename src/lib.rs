@@ -0,0 +1,52 @@
+//! Sokoban game engine.
+//!
+//! The engine parses levels and their descriptive information ([`map`], [`info`]), applies the movement rules ([`movement`]),
+//! tracks a level being played including undo, redo and scoring ([`session`]),
+//! loads level collections in the `maps.txt`, XSB, SLC and RLE formats
+//! ([`mapmanager`], [`xsb`], [`slc`], [`rle`]) from the configured level sources ([`sources`])
+//! and checks them for problems ([`validation`]).
+//! Solutions in LURD notation can be replayed and verified ([`solution`]) or searched for ([`solver`]),
+//! and positions which can no longer be solved are detected ([`deadlock`], [`analysis`]).
+//! The terminal game in `main.rs` is a thin frontend on top of this crate.
+#![warn(missing_docs)]
+
+/// Descriptive information about levels and collections.
+pub mod info;
+/// Level representation and the `maps.txt` level notation.
+pub mod map;
+/// Loading of level collections.
+pub mod mapmanager;
+/// Movement rules for the player and the blocks.
+pub mod movement;
+/// State of a level being played.
+pub mod session;
+/// Level checks run while loading.
+pub mod validation;
+/// Run-length encoded level strings.
+pub mod rle;
+/// Reading of SLC (XML) level collections.
+pub mod slc;
+/// Import and verification of LURD solutions.
+pub mod solution;
+/// Detection of dead squares and blocks which can no longer be moved.
+pub mod deadlock;
+/// Detection of frozen block groups and closed corrals.
+pub mod analysis;
+/// Cell numbering and walking on the static part of a level, for the analysis and the solver.
+mod board;
+/// Search for level solutions.
+pub mod solver;
+/// Level sources from the command line, the environment and the config file.
+pub mod sources;
+/// Reading and writing of the XSB level notation.
+pub mod xsb;
+/// Helpers shared by the unit tests.
+#[cfg(test)]
+mod test_util;
+
+pub use info::{CollectionInfo, LevelInfo};
+pub use map::{LevelMetadata, Map, MapCell, MapFormat, MapTile, Position};
+pub use mapmanager::{DefaultMapContentProvider, EmbeddedMapContentProvider, FileMapContentProvider, LevelCollection, MapContentProvider, MapManager,
+    StdinMapContentProvider, StringMapContentProvider};
+pub use movement::MoveDirection;
+pub use session::LevelSession;
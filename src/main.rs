@@ -1,13 +1,12 @@
+use std::env;
+use std::ffi::OsString;
 use std::io;
+use std::path::PathBuf;
+
+use sokoban::sources;
 
 mod game;
 use game::Game;
-mod map;
-use map::{Map, MapTile, Position};
-mod mapmanager;
-use mapmanager::{DefaultMapContentProvider, MapManager};
-mod movement;
-use movement::MoveDirection;
 mod input;
 mod drawing;
 use drawing::{TerminalDrawer, Draw};
@@ -15,9 +14,27 @@ mod platform;
 use platform::PlatformSpecific;
 
 fn main() -> Result<(), io::Error> {
+    let mut args: Vec<OsString> = env::args_os().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "play") {
+        args.remove(0);
+    }
+    let solution_file = match args.first() {
+        Some(arg) if arg == "--verify" && args.len() > 1 => {
+            args.remove(0);
+            Some(PathBuf::from(args.remove(0)))
+        }
+        _ => None,
+    };
+    let level_sources = sources::level_sources(args)?;
     let mut game = Game::new();
+    if let Some(solution_file) = solution_file {
+        for report in game.verify_solutions(&solution_file, &level_sources)? {
+            println!("{}", report);
+        }
+        return Ok(());
+    }
     let mut platform = PlatformSpecific::new_terminal_platform();
-    game.init(&platform)?;
+    game.init(&platform, &level_sources)?;
 
     game.main_loop(&mut platform);
     game.tear_down(&platform);
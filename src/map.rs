@@ -1,35 +1,213 @@
-pub const MAX_MAP_DIM: usize = 30;
-
 use crate::{MoveDirection, movement};
+use crate::info::LevelInfo;
 
+/// A single Sokoban level: the static tiles plus the player and the movable blocks.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Map {
-    pub map: [[MapTile; MAX_MAP_DIM]; MAX_MAP_DIM],
+    /// Static tiles of the level. Blocks and the player are not stored in the grid.
+    pub map: Grid,
+    /// Current position of the player.
     pub player_position: Position,
+    /// All blocks of the level, in the order they were parsed.
     pub movable_blocks: Vec<MovableBlock>,
+    /// Number of blocks currently standing on a target zone.
     pub movable_blocks_in_final_position: u32,
-    pub id: u32
+    /// Index of the level within its collection.
+    pub id: u32,
+    /// Title, author, comment and other information about the level, if the collection provides them.
+    pub info: LevelInfo,
+    /// Header of the level in `maps.txt`, if it has one.
+    pub metadata: Option<LevelMetadata>,
+}
+
+/// The header lines of a level in `maps.txt`.
+///
+/// Each field is `None` if the corresponding line is missing. Levels from SLC collections
+/// only have the sizes, taken from the `Width` and `Height` attributes.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct LevelMetadata {
+    /// Number of the level from the `Maze:` line.
+    pub maze: Option<u32>,
+    /// Raw value of the `File offset:` line.
+    pub file_offset: Option<String>,
+    /// Declared width from the `Size X:` line.
+    pub size_x: Option<usize>,
+    /// Declared height from the `Size Y:` line.
+    pub size_y: Option<usize>,
+    /// Raw value of the `End:` line.
+    pub end: Option<String>,
+    /// Value of the `Length:` line.
+    pub length: Option<u32>,
 }
 
+/// Static tile of a level.
+///
+/// The grid of a [`Map`] only holds `Space`, `Wall` and `TargetZone`, `Outside` is returned
+/// by [`Map::get_tile_type_for_position`] for positions beyond the map bounds. Blocks and
+/// the player are not tiles, see [`MapCell`].
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum MapTile {
+    /// Empty floor.
     Space,
+    /// Wall, neither the player nor a block can enter it.
     Wall,
-    Block,
+    /// Goal square a block has to be pushed onto.
     TargetZone,
+    /// Anything beyond the bounds of the map.
+    Outside,
+}
+
+/// Content of a cell as drawn or written: the static tile together with the player or a block on it.
+///
+/// Produced by [`Map::get_cell_for_position`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MapCell {
+    /// Empty floor.
+    Space,
+    /// Wall.
+    Wall,
+    /// Empty target zone.
+    TargetZone,
+    /// Block on empty floor.
+    Block,
+    /// Block standing on a target zone.
+    BlockOnTargetZone,
+    /// Player on empty floor.
+    Player,
+    /// Player standing on a target zone.
+    PlayerOnTargetZone,
+    /// Anything beyond the bounds of the map.
+    Outside,
+}
+
+impl From<MapTile> for MapCell {
+    fn from(tile: MapTile) -> MapCell {
+        match tile {
+            MapTile::Space => MapCell::Space,
+            MapTile::Wall => MapCell::Wall,
+            MapTile::TargetZone => MapCell::TargetZone,
+            MapTile::Outside => MapCell::Outside,
+        }
+    }
+}
+
+/// Rectangular tile grid that grows to whatever size the level needs.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    tiles: Vec<MapTile>,
+}
+
+impl Grid {
+    /// Creates a grid of the given size filled with [`MapTile::Space`].
+    pub fn with_size(width: usize, height: usize) -> Grid {
+        Grid { width, height, tiles: vec![MapTile::Space; width * height] }
+    }
+
+    /// Number of columns.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of rows.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the tile at `x`/`y`, panicking if it is out of range.
+    pub fn get(&self, x: usize, y: usize) -> MapTile {
+        assert!(x < self.width && y < self.height, "position ({}, {}) is outside of the {}x{} grid", x, y, self.width, self.height);
+        self.tiles[y * self.width + x]
+    }
+
+    /// Returns the tile at `x`/`y`, or `None` if it is out of range.
+    pub fn try_get(&self, x: i32, y: i32) -> Option<MapTile> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        Some(self.get(x as usize, y as usize))
+    }
+
+    /// Sets the tile at `x`/`y`, growing the grid if necessary.
+    pub fn set(&mut self, x: usize, y: usize, tile: MapTile) {
+        if x >= self.width || y >= self.height {
+            self.resize(self.width.max(x + 1), self.height.max(y + 1));
+        }
+        self.tiles[y * self.width + x] = tile;
+    }
+
+    /// Iterates over the rows of the grid from top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = &[MapTile]> {
+        self.tiles.chunks(self.width.max(1))
+    }
+
+    fn resize(&mut self, width: usize, height: usize) {
+        let mut resized = Grid::with_size(width, height);
+        for (y, row) in self.rows().enumerate() {
+            resized.tiles[y * width..y * width + row.len()].copy_from_slice(row);
+        }
+        *self = resized;
+    }
 }
 
+/// Text notation of a level.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MapFormat {
+    /// The notation of `data/maps/maps.txt`: `X` wall, `*` block, `&` block on a target zone.
+    Legacy,
+    /// The community XSB notation: `#` wall, `$` block, `*` block on a target zone.
+    Xsb,
+}
+
+impl MapFormat {
+    /// Returns the static tile of a level character and whether it holds a block and the player.
+    fn parse_char(self, c: char) -> (MapTile, bool, bool) {
+        match (self, c) {
+            (MapFormat::Legacy, 'X') | (MapFormat::Xsb, '#') => (MapTile::Wall, false, false),
+            (MapFormat::Legacy, '*') | (MapFormat::Xsb, '$') => (MapTile::Space, true, false),
+            (MapFormat::Legacy, '&') | (MapFormat::Xsb, '*') => (MapTile::TargetZone, true, false),
+            (_, '.') => (MapTile::TargetZone, false, false),
+            (_, '@') => (MapTile::Space, false, true),
+            (_, '+') => (MapTile::TargetZone, false, true),
+            _ => (MapTile::Space, false, false),
+        }
+    }
+
+    /// Returns the character of a cell as returned by [`Map::get_cell_for_position`].
+    pub fn format_cell(self, cell: MapCell) -> char {
+        match (self, cell) {
+            (MapFormat::Legacy, MapCell::Wall) => 'X',
+            (MapFormat::Xsb, MapCell::Wall) => '#',
+            (MapFormat::Legacy, MapCell::Block) => '*',
+            (MapFormat::Xsb, MapCell::Block) => '$',
+            (MapFormat::Legacy, MapCell::BlockOnTargetZone) => '&',
+            (MapFormat::Xsb, MapCell::BlockOnTargetZone) => '*',
+            (_, MapCell::TargetZone) => '.',
+            (_, MapCell::Player) => '@',
+            (_, MapCell::PlayerOnTargetZone) => '+',
+            (_, MapCell::Space) | (_, MapCell::Outside) => ' ',
+        }
+    }
+}
+
+/// Position on the map, `x` is the column and `y` the row.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Position {
+    /// Column, counted from the left.
     pub x: i32,
+    /// Row, counted from the top.
     pub y: i32,
 }
+/// A block the player can push.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct MovableBlock {
+    /// Current position of the block.
     pub position: Position,
 }
 
 impl MovableBlock {
+    /// Moves the block one step into `move_direction`.
     pub fn move_to(&mut self, move_direction: &MoveDirection)
     {
         self.position = movement::calc_new_position_after_movement(move_direction, &self.position);
@@ -37,56 +215,125 @@ impl MovableBlock {
 }
 
 impl Map {
+    /// Parses one row of a level in the `maps.txt` notation.
+    ///
+    /// `X` is a wall, `*` a block, `.` a target zone, `&` a block on a target zone,
+    /// `@` the player and `+` the player on a target zone.
     pub fn parse_single_line(&mut self, line: &str, line_idx: usize) {
+        self.parse_single_line_with_format(line, line_idx, MapFormat::Legacy);
+    }
+
+    /// Parses one row of a level in the given notation.
+    pub fn parse_single_line_with_format(&mut self, line: &str, line_idx: usize, format: MapFormat) {
         for (idx, c) in line.chars().enumerate() {
-            let mut tile = MapTile::Space;
-            match c {
-                'X' => tile = MapTile::Wall,
-                '*' => self.movable_blocks.push(MovableBlock {position: Position {y: line_idx as i32, x: idx as i32}}),
-                '.' => tile = MapTile::TargetZone,
-                '@' => {
-                    self.player_position = Position {
-                        x: idx as i32,
-                        y: line_idx as i32,
-                    }
-                }
-                ' ' => (),
-                _ => (),
+            let position = Position { x: idx as i32, y: line_idx as i32 };
+            let (tile, has_block, has_player) = format.parse_char(c);
+            if has_block {
+                self.movable_blocks.push(MovableBlock { position });
             }
-            self.map[line_idx][idx] = tile;
+            if has_player {
+                self.player_position = position;
+            }
+            self.map.set(idx, line_idx, tile);
         }
     }
 
-    pub fn parse_map_block(&mut self, input_map_block: &Vec<&str>) {
+    /// Parses all rows of a level and counts the blocks already on a target zone.
+    pub fn parse_map_block(&mut self, input_map_block: &[&str]) {
+        self.parse_map_block_with_format(input_map_block, MapFormat::Legacy);
+    }
+
+    /// Parses all rows of a level in the given notation and counts the blocks already on a target zone.
+    pub fn parse_map_block_with_format(&mut self, input_map_block: &[&str], format: MapFormat) {
         for (line_idx, line) in input_map_block.iter().enumerate() {
-            self.parse_single_line(line, line_idx);
+            self.parse_single_line_with_format(line, line_idx, format);
         }
+        self.update_movable_blocks_in_final_position();
     }
 
+    /// Writes the rows of the level in the given notation, all padded to the width of the map.
+    pub fn write_rows(&self, format: MapFormat) -> Vec<String> {
+        (0..self.height())
+            .map(|y| (0..self.width())
+                .map(|x| format.format_cell(self.get_cell_for_position(&Position { x: x as i32, y: y as i32 })))
+                .collect())
+            .collect()
+    }
+
+    /// Recounts [`Map::movable_blocks_in_final_position`] from the current block positions.
+    pub fn update_movable_blocks_in_final_position(&mut self) {
+        self.movable_blocks_in_final_position = self.movable_blocks.iter()
+            .filter(|block| self.get_tile_type_for_position(&block.position) == MapTile::TargetZone)
+            .count() as u32;
+    }
+
+    /// Returns whether all blocks stand on a target zone.
+    pub fn is_solved(&self) -> bool {
+        self.movable_blocks_in_final_position == self.movable_blocks.len() as u32
+    }
+
+    /// Returns whether a block stands at `position`.
     pub fn is_movable_block_at(&self, position: &Position) -> bool {
-        for block in self.movable_blocks.iter() {
-            if block.position == *position {
-                return true;
-            }
-        }
-        return false;
+        self.movable_blocks.iter().any(|block| block.position == *position)
     }
 
+    /// Returns the block standing at `position`.
     pub fn get_movable_block_at(&mut self, position: &Position) -> Option<&mut MovableBlock> {
-        for block in self.movable_blocks.iter_mut() {
-            if block.position == *position {
-                return Some(block);
-            }
-        }
-        None
+        self.movable_blocks.iter_mut().find(|block| block.position == *position)
+    }
+
+    /// Returns the index into [`Map::movable_blocks`] of the block standing at `position`.
+    pub fn get_movable_block_index_at(&self, position: &Position) -> Option<usize> {
+        self.movable_blocks.iter().position(|block| block.position == *position)
+    }
+
+    /// Number of the level shown to the player: the `Maze:` number if known, otherwise the id counted from 1.
+    pub fn display_number(&self) -> u32 {
+        self.metadata.as_ref().and_then(|metadata| metadata.maze).unwrap_or(self.id + 1)
+    }
+
+    /// Width of the level in tiles.
+    pub fn width(&self) -> usize {
+        self.map.width()
     }
 
+    /// Height of the level in tiles.
+    pub fn height(&self) -> usize {
+        self.map.height()
+    }
+
+    /// Returns the static tile at `position`, or [`MapTile::Outside`] beyond the map bounds.
     pub fn get_tile_type_for_position(&self, position: &Position) -> MapTile {
-        self.map[position.y as usize][position.x as usize]
+        self.map.try_get(position.x, position.y).unwrap_or(MapTile::Outside)
     }
 
+    /// Returns the cell at `position` including the player and the blocks.
+    pub fn get_cell_for_position(&self, position: &Position) -> MapCell {
+        let tile = self.get_tile_type_for_position(position);
+        let on_target = tile == MapTile::TargetZone;
+        if self.is_movable_block_at(position) {
+            if on_target { MapCell::BlockOnTargetZone } else { MapCell::Block }
+        } else if self.player_position == *position {
+            if on_target { MapCell::PlayerOnTargetZone } else { MapCell::Player }
+        } else {
+            MapCell::from(tile)
+        }
+    }
+
+    /// Creates an empty map without any tiles.
     pub fn new() -> Map {
-        return Map { map: [[MapTile::Space; MAX_MAP_DIM]; MAX_MAP_DIM], player_position: Position {x: 0, y: 0}, movable_blocks: Vec::new(), movable_blocks_in_final_position:0, id:0};
+        Map::with_size(0, 0)
+    }
+
+    /// Creates a map of the given size filled with [`MapTile::Space`].
+    pub fn with_size(width: usize, height: usize) -> Map {
+        Map { map: Grid::with_size(width, height), player_position: Position {x: 0, y: 0}, movable_blocks: Vec::new(), movable_blocks_in_final_position:0, id:0, info: LevelInfo::default(), metadata: None}
+    }
+}
+
+impl Default for Map {
+    fn default() -> Map {
+        Map::new()
     }
 }
 
@@ -97,21 +344,92 @@ mod tests {
     #[test]
     fn test_get_new_map() {
         let map_to_test = Map::new();
-        assert_eq!(true, map_to_test.movable_blocks.is_empty());
+        assert!(map_to_test.movable_blocks.is_empty());
         assert_eq!(0, map_to_test.movable_blocks_in_final_position);
         assert_eq!(0, map_to_test.id);
-        for y in 0..MAX_MAP_DIM {
-            for x in 0..MAX_MAP_DIM {
-                assert_eq!(MapTile::Space, map_to_test.get_tile_type_for_position(&Position { x: x as i32, y: y as i32}));
+        assert_eq!(0, map_to_test.width());
+        assert_eq!(0, map_to_test.height());
+    }
+
+    #[test]
+    fn test_get_map_with_size() {
+        let map_to_test = Map::with_size(3, 2);
+        assert_eq!(3, map_to_test.width());
+        assert_eq!(2, map_to_test.height());
+        for y in 0..2 {
+            for x in 0..3 {
+                assert_eq!(MapTile::Space, map_to_test.get_tile_type_for_position(&Position { x, y }));
             }
         }
     }
 
+    #[test]
+    fn test_grid_grows_on_set() {
+        let mut grid = Grid::with_size(2, 1);
+        grid.set(0, 0, MapTile::Wall);
+        grid.set(3, 2, MapTile::TargetZone);
+        assert_eq!(4, grid.width());
+        assert_eq!(3, grid.height());
+        assert_eq!(MapTile::Wall, grid.get(0, 0));
+        assert_eq!(MapTile::TargetZone, grid.get(3, 2));
+        assert_eq!(MapTile::Space, grid.get(1, 0));
+        assert_eq!(3, grid.rows().count());
+    }
+
+    #[test]
+    fn test_get_tile_type_outside_of_map() {
+        let map = Map::with_size(2, 2);
+        assert_eq!(MapTile::Outside, map.get_tile_type_for_position(&Position { x: -1, y: 0 }));
+        assert_eq!(MapTile::Outside, map.get_tile_type_for_position(&Position { x: 0, y: -1 }));
+        assert_eq!(MapTile::Outside, map.get_tile_type_for_position(&Position { x: 2, y: 0 }));
+        assert_eq!(MapTile::Outside, map.get_tile_type_for_position(&Position { x: 0, y: 2 }));
+        assert_eq!(MapCell::Outside, map.get_cell_for_position(&Position { x: 5, y: 5 }));
+        assert_eq!(MapTile::Space, map.get_tile_type_for_position(&Position { x: 1, y: 1 }));
+    }
+
+    #[test]
+    fn test_parse_map_wider_than_30() {
+        let mut map = Map::new();
+        let wide_line = format!("X@{}.X", " ".repeat(40));
+        map.parse_map_block(&[&wide_line, "X"]);
+        assert_eq!(44, map.width());
+        assert_eq!(2, map.height());
+        assert_eq!(MapTile::TargetZone, map.get_tile_type_for_position(&Position { x: 42, y: 0 }));
+        assert_eq!(MapTile::Space, map.get_tile_type_for_position(&Position { x: 43, y: 1 }));
+        assert_eq!(MapTile::Outside, map.get_tile_type_for_position(&Position { x: 44, y: 0 }));
+    }
+
+    #[test]
+    fn test_display_number() {
+        let mut map = Map::new();
+        map.id = 4;
+        assert_eq!(5, map.display_number());
+        map.metadata = Some(LevelMetadata::default());
+        assert_eq!(5, map.display_number());
+        map.metadata = Some(LevelMetadata { maze: Some(12), ..LevelMetadata::default() });
+        assert_eq!(12, map.display_number());
+    }
+
+    #[test]
+    fn test_is_solved() {
+        let mut map = Map::with_size(2, 1);
+        map.movable_blocks.push(MovableBlock { position: Position {x: 0, y: 0} });
+        map.movable_blocks_in_final_position = 1;
+        assert!(map.is_solved());
+    }
+
+    #[test]
+    fn test_is_not_solved() {
+        let mut map = Map::with_size(2, 1);
+        map.movable_blocks.push(MovableBlock { position: Position {x: 0, y: 0} });
+        assert!(!map.is_solved());
+    }
+
     #[test]
     fn test_is_movable_block_at() {
         let mut map = Map::new();
         map.movable_blocks.push(MovableBlock { position: Position { x: 5, y: 5 } });
-        assert_eq!(true, map.is_movable_block_at(&Position { x: 5, y: 5 }));
+        assert!(map.is_movable_block_at(&Position { x: 5, y: 5 }));
     }
 
     #[test]
@@ -119,12 +437,10 @@ mod tests {
         let mut map = Map::new();
         let first_line = String::from(" X@*.");
         let second_line = String::from("X");
-        let mut block_input: Vec<&str> = Vec::new();
-        block_input.push(&first_line);
-        block_input.push(&second_line);
+        let block_input: Vec<&str> = vec![&first_line, &second_line];
         map.parse_map_block(&block_input);
 
-        assert_eq!(true, map.is_movable_block_at(&Position { x: 3, y: 0 }));
+        assert!(map.is_movable_block_at(&Position { x: 3, y: 0 }));
         assert_eq!(
             MapTile::Space,
             map.get_tile_type_for_position(&Position { x: 0, y: 0 })
@@ -144,6 +460,43 @@ mod tests {
         assert_eq!(Position { x: 2, y: 0 }, map.player_position);
     }
 
+    #[test]
+    fn test_parse_map_block_with_objects_on_target_zone() {
+        let mut map = Map::new();
+        let block_input = vec!["X&+*.X"];
+        map.parse_map_block(&block_input);
+
+        assert_eq!(2, map.movable_blocks.len());
+        assert_eq!(1, map.movable_blocks_in_final_position);
+        assert_eq!(Position { x: 2, y: 0 }, map.player_position);
+        assert_eq!(MapTile::TargetZone, map.get_tile_type_for_position(&Position { x: 1, y: 0 }));
+        assert_eq!(MapTile::TargetZone, map.get_tile_type_for_position(&Position { x: 2, y: 0 }));
+        assert_eq!(MapCell::BlockOnTargetZone, map.get_cell_for_position(&Position { x: 1, y: 0 }));
+        assert_eq!(MapCell::PlayerOnTargetZone, map.get_cell_for_position(&Position { x: 2, y: 0 }));
+        assert_eq!(MapCell::Block, map.get_cell_for_position(&Position { x: 3, y: 0 }));
+        assert_eq!(MapCell::TargetZone, map.get_cell_for_position(&Position { x: 4, y: 0 }));
+    }
+
+    #[test]
+    fn test_parse_xsb_map_block() {
+        let mut xsb_map = Map::new();
+        xsb_map.parse_map_block_with_format(&["-#####", "#@$.*#", "#. -_#", "######"], MapFormat::Xsb);
+        let mut legacy_map = Map::new();
+        legacy_map.parse_map_block(&[" XXXXX", "X@*.&X", "X.   X", "XXXXXX"]);
+        assert_eq!(legacy_map, xsb_map);
+        assert_eq!(2, xsb_map.movable_blocks.len());
+        assert_eq!(1, xsb_map.movable_blocks_in_final_position);
+    }
+
+    #[test]
+    fn test_write_rows() {
+        let rows = vec![" XXXXX", "X@*.&X", "X. . X", "XXXXXX"];
+        let mut map = Map::new();
+        map.parse_map_block(&rows);
+        assert_eq!(rows, map.write_rows(MapFormat::Legacy));
+        assert_eq!(vec![" #####", "#@$.*#", "#. . #", "######"], map.write_rows(MapFormat::Xsb));
+    }
+
     #[test]
     fn test_move_movable_block() {
         let mut block = MovableBlock{position: Position { x: 5, y: 6 }};
@@ -159,10 +512,19 @@ mod tests {
         let result = map.get_movable_block_at(&Position{y: 7, x: 5});
         assert_eq!(result.unwrap(), &MovableBlock {position: Position {y: 7, x: 5}});
     }
+    #[test]
+    fn test_get_movable_block_index_at() {
+        let mut map = Map::new();
+        map.movable_blocks.push(MovableBlock {position: Position {y: 1, x: 1}});
+        map.movable_blocks.push(MovableBlock {position: Position {y: 7, x: 5}});
+        assert_eq!(Some(1), map.get_movable_block_index_at(&Position{y: 7, x: 5}));
+        assert_eq!(None, map.get_movable_block_index_at(&Position{y: 0, x: 0}));
+    }
+
     #[test]
     fn test_get_movable_block_at_failure() {
         let mut map = Map::new();
         let result = map.get_movable_block_at(&Position{x: 0, y: 0});
-        assert_eq!(result.is_none(), true);
+        assert!(result.is_none());
     }
 }
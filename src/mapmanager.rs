@@ -1,14 +1,43 @@
 use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
 use regex::Regex;
-use std::io;
-use crate::map::Map;
+use std::io::{self, Read};
+use crate::info::{CollectionInfo, LevelInfo};
+use crate::map::{LevelMetadata, Map, MapFormat};
+use crate::rle;
+use crate::slc;
+use crate::validation::{self, ValidationError};
+use crate::xsb;
 
 const MAP_PATH : &str = "data/maps/maps.txt";
+const EMBEDDED_MAPS : &str = include_str!("../data/maps/maps.txt");
+const LEGACY_DIVIDER : &str = "*************************************";
 
+/// Loads a level collection and keeps the validation results of each level.
 pub struct MapManager {
+    /// The levels in collection order.
     pub maps: Vec<Map>,
+    /// Problems found while loading, see [`validation::validate_map_block`].
+    pub validation_errors: Vec<ValidationError>,
+    /// The collections the levels were read from, one per read call.
+    pub collections: Vec<LevelCollection>,
 }
 
+/// A named group of consecutive levels in [`MapManager::maps`].
+///
+/// The levels themselves carry their title, author, comment and difficulty in [`Map::info`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LevelCollection {
+    /// Name of the source, see [`MapContentProvider::collection_name`].
+    pub name: String,
+    /// Information about the collection, if the source provides it.
+    pub info: CollectionInfo,
+    /// Ids of the levels of this collection.
+    pub map_ids: Range<u32>,
+}
+
+/// Reads the collection shipped in `data/maps/maps.txt`, relative to the working directory.
 pub struct DefaultMapContentProvider {}
 
 impl MapContentProvider for DefaultMapContentProvider {
@@ -16,37 +45,333 @@ impl MapContentProvider for DefaultMapContentProvider {
     {
         fs::read_to_string(MAP_PATH)
     }
+
+    fn collection_name(&self) -> String {
+        String::from("maps")
+    }
+}
+/// Returns the collection shipped in `data/maps/maps.txt` as compiled into the binary,
+/// so the game is playable from any working directory.
+pub struct EmbeddedMapContentProvider {}
+
+impl MapContentProvider for EmbeddedMapContentProvider {
+    fn get_maps(&self) -> Result<String,io::Error>
+    {
+        Ok(String::from(EMBEDDED_MAPS))
+    }
+
+    fn collection_name(&self) -> String {
+        String::from("maps")
+    }
+}
+
+/// Reads a collection from a file in any supported notation.
+pub struct FileMapContentProvider {
+    /// Path of the collection file.
+    pub path: PathBuf,
 }
+
+impl MapContentProvider for FileMapContentProvider {
+    fn get_maps(&self) -> Result<String,io::Error>
+    {
+        fs::read_to_string(&self.path)
+    }
+
+    fn collection_name(&self) -> String {
+        self.path.file_stem().map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+    }
+}
+
+/// Reads levels piped into standard input.
+pub struct StdinMapContentProvider {}
+
+impl MapContentProvider for StdinMapContentProvider {
+    fn get_maps(&self) -> Result<String,io::Error>
+    {
+        let mut map_contents = String::new();
+        io::stdin().read_to_string(&mut map_contents)?;
+        Ok(map_contents)
+    }
+
+    fn collection_name(&self) -> String {
+        String::from("stdin")
+    }
+}
+
+/// Returns levels given as text, e.g. pasted on the command line.
+pub struct StringMapContentProvider {
+    /// The levels in any supported notation.
+    pub map_contents: String,
+}
+
+impl MapContentProvider for StringMapContentProvider {
+    fn get_maps(&self) -> Result<String,io::Error>
+    {
+        Ok(self.map_contents.clone())
+    }
+}
+
+/// Source of level collection text.
 pub trait MapContentProvider {
+    /// Returns the raw text of the collection.
     fn get_maps(&self) -> Result<String,io::Error>;
+
+    /// Name of the collection, empty if the source has none.
+    fn collection_name(&self) -> String {
+        String::new()
+    }
+}
+
+impl LevelCollection {
+    /// The collection title, or the name of its source if it has no title.
+    pub fn display_title(&self) -> &str {
+        self.info.title.as_deref().unwrap_or(&self.name)
+    }
+}
+
+impl Default for MapManager {
+    fn default() -> MapManager {
+        MapManager::new()
+    }
 }
 
 impl MapManager {
-    pub fn read_maps(&mut self, map_content_provider: impl MapContentProvider) -> Result<(), io::Error> {        
-        let regex_mapcontent = Regex::new(r"^[ X]+[ X*@\.&]+").unwrap();
-        let regex_divider = Regex::new(r"^\*+").unwrap();
+    /// Creates a manager without any levels.
+    pub fn new() -> MapManager {
+        MapManager { maps: Vec::new(), validation_errors: Vec::new(), collections: Vec::new() }
+    }
 
-        let map_contents = map_content_provider.get_maps()?;
-        let mut currentmap = Map::new();
-        let mut map_block : Vec<&str> = Vec::new();
-        for line in map_contents.lines() {
-            if regex_mapcontent.is_match(line) {
-                map_block.push(line);
-            } else if regex_divider.is_match(line) {
-                currentmap.parse_map_block(&map_block);
-                currentmap.id = self.maps.len() as u32;
-                self.maps.push(currentmap);
-                currentmap = Map::new();
-                map_block.clear();
+    /// Appends all levels of the text returned by `map_content_provider`, detecting its notation.
+    /// SLC (XML) collections and run-length encoded levels, one per line, are recognised as well.
+    ///
+    /// Invalid levels are still loaded and their problems recorded in [`MapManager::validation_errors`].
+    pub fn read_maps(&mut self, map_content_provider: impl MapContentProvider) -> Result<(), io::Error> {
+        self.read_collection(map_content_provider, |map_manager, map_contents| {
+            if slc::is_slc(map_contents) {
+                return map_manager.parse_slc_maps(map_contents);
             }
+            if is_rle_collection(map_contents) {
+                return map_manager.parse_rle_maps(map_contents);
+            }
+            map_manager.parse_maps(map_contents, detect_format(map_contents));
+            Ok(())
+        })
+    }
+
+    /// Appends the run-length encoded levels returned by `map_content_provider`, one per non-empty line.
+    pub fn read_rle_maps(&mut self, map_content_provider: impl MapContentProvider) -> Result<(), io::Error> {
+        self.read_collection(map_content_provider, MapManager::parse_rle_maps)
+    }
+
+    fn parse_rle_maps(&mut self, map_contents: &str) -> Result<(), io::Error> {
+        for (line_idx, line) in map_contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let rows = rle::decode(line)?;
+            let map_block: Vec<&str> = rows.iter().map(String::as_str).collect();
+            let nof_errors = self.validation_errors.len();
+            self.add_map(&map_block, line_idx + 1, MapFormat::Xsb, LevelInfo::default(), None);
+            for error in self.validation_errors[nof_errors..].iter_mut() {
+                error.line = line_idx + 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends all levels of the SLC collection returned by `map_content_provider`
+    /// and takes over the information about the collection.
+    pub fn read_slc_maps(&mut self, map_content_provider: impl MapContentProvider) -> Result<(), io::Error> {
+        self.read_collection(map_content_provider, MapManager::parse_slc_maps)
+    }
+
+    fn parse_slc_maps(&mut self, map_contents: &str) -> Result<(), io::Error> {
+        let collection = slc::parse(map_contents)?;
+        if let Some(current) = self.collections.last_mut() {
+            current.info = collection.info;
+        }
+        for level in collection.levels {
+            let metadata = LevelMetadata { size_x: level.width, size_y: level.height, ..LevelMetadata::default() };
+            self.add_map(&level.rows, level.first_line, MapFormat::Xsb, level.info, Some(metadata));
         }
         Ok(())
     }
+
+    /// Like [`MapManager::read_maps`], but with a declared notation.
+    pub fn read_maps_as(&mut self, map_content_provider: impl MapContentProvider, format: MapFormat) -> Result<(), io::Error> {
+        self.read_collection(map_content_provider, |map_manager, map_contents| {
+            map_manager.parse_maps(map_contents, format);
+            Ok(())
+        })
+    }
+
+    fn parse_maps(&mut self, map_contents: &str, format: MapFormat) {
+        match format {
+            MapFormat::Legacy => {
+                for level in split_legacy_levels(map_contents) {
+                    self.add_map(&level.rows, level.first_line, format, LevelInfo::default(), Some(level.metadata));
+                }
+            }
+            MapFormat::Xsb => {
+                let collection = xsb::parse_collection(map_contents);
+                if let Some(current) = self.collections.last_mut() {
+                    current.info = collection.info;
+                }
+                for level in collection.levels {
+                    self.add_map(&level.rows, level.first_line, format, level.info, None);
+                }
+            }
+        }
+    }
+
+    /// Starts a new collection and fills it with `parse`. If `parse` fails, the collection
+    /// and any levels it already added are removed again.
+    fn read_collection(&mut self, map_content_provider: impl MapContentProvider,
+            parse: impl FnOnce(&mut MapManager, &str) -> Result<(), io::Error>) -> Result<(), io::Error> {
+        let map_contents = map_content_provider.get_maps()?;
+        let (nof_maps, nof_errors) = (self.maps.len(), self.validation_errors.len());
+        self.start_collection(&map_content_provider);
+        let result = parse(self, &map_contents);
+        if result.is_err() {
+            self.maps.truncate(nof_maps);
+            self.validation_errors.truncate(nof_errors);
+            self.collections.pop();
+        }
+        result
+    }
+
+    fn start_collection(&mut self, map_content_provider: &impl MapContentProvider) {
+        let first_id = self.maps.len() as u32;
+        self.collections.push(LevelCollection { name: map_content_provider.collection_name(), info: CollectionInfo::default(), map_ids: first_id..first_id });
+    }
+
+    fn add_map(&mut self, map_block: &[&str], first_line: usize, format: MapFormat, info: LevelInfo, metadata: Option<LevelMetadata>) {
+        let mut map = Map::new();
+        map.parse_map_block_with_format(map_block, format);
+        map.id = self.maps.len() as u32;
+        map.info = info;
+        map.metadata = metadata;
+        if let Err(errors) = validation::validate_map_block(map_block, &map, first_line) {
+            self.validation_errors.extend(errors);
+        }
+        self.maps.push(map);
+        if let Some(current) = self.collections.last_mut() {
+            current.map_ids.end = self.maps.len() as u32;
+        }
+    }
+
+    /// Returns the levels of `collection`.
+    pub fn maps_of(&self, collection: &LevelCollection) -> &[Map] {
+        &self.maps[collection.map_ids.start as usize..collection.map_ids.end as usize]
+    }
+
+    /// Returns the collection the level with the given id was read from.
+    pub fn collection_for_map(&self, map_id: u32) -> Option<&LevelCollection> {
+        self.collections.iter().find(|collection| collection.map_ids.contains(&map_id))
+    }
+
+    /// Returns the validation problems of the level with the given id.
+    pub fn validation_errors_for_map(&self, map_id: u32) -> impl Iterator<Item = &ValidationError> {
+        self.validation_errors.iter().filter(move |error| error.level_idx == map_id as usize)
+    }
+}
+
+/// Number of consecutive XSB rows [`detect_format`] needs to see: a level has at least a row
+/// of wall above and below the player.
+pub const MIN_XSB_LEVEL_ROWS: usize = 3;
+
+/// Guesses the notation of a collection: XSB if it has a run of at least [`MIN_XSB_LEVEL_ROWS`]
+/// consecutive XSB rows, otherwise `maps.txt`.
+///
+/// A single line like `#` in a `maps.txt` header or comment is not enough to switch to XSB.
+pub fn detect_format(map_contents: &str) -> MapFormat {
+    if xsb::split_levels(map_contents).iter().any(|(_, rows)| rows.len() >= MIN_XSB_LEVEL_ROWS) {
+        MapFormat::Xsb
+    } else {
+        MapFormat::Legacy
+    }
+}
+
+fn is_rle_collection(map_contents: &str) -> bool {
+    let mut lines = map_contents.lines().filter(|line| !line.trim().is_empty()).peekable();
+    let regex_rle = rle::regex_rle();
+    lines.peek().is_some() && lines.all(|line| rle::is_rle(&regex_rle, line))
+}
+
+/// Writes a collection in the `maps.txt` notation, including the headers
+/// and the `*****` divider after each level.
+///
+/// `Maze:` and `Size` are always written, the other header lines only if the level's
+/// [`LevelMetadata`] has them.
+pub fn write_legacy_maps(maps: &[Map]) -> String {
+    let mut map_contents = String::new();
+    for map in maps {
+        let metadata = map.metadata.clone().unwrap_or_default();
+        map_contents += &format!("Maze: {}\n", map.display_number());
+        if let Some(file_offset) = metadata.file_offset {
+            map_contents += &format!("File offset: {}\n", file_offset);
+        }
+        map_contents += &format!("Size X: {}\nSize Y: {}\n", map.width(), map.height());
+        if let Some(end) = metadata.end {
+            map_contents += &format!("End: {}\n", end);
+        }
+        if let Some(length) = metadata.length {
+            map_contents += &format!("Length: {}\n", length);
+        }
+        map_contents += "\n";
+        for row in map.write_rows(MapFormat::Legacy) {
+            map_contents += &format!("{}\n", row);
+        }
+        map_contents += &format!("\n{}\n", LEGACY_DIVIDER);
+    }
+    map_contents
+}
+
+struct LegacyLevel<'a> {
+    first_line: usize,
+    rows: Vec<&'a str>,
+    metadata: LevelMetadata,
+}
+
+fn split_legacy_levels(map_contents: &str) -> Vec<LegacyLevel<'_>> {
+    let regex_mapcontent = Regex::new(r"^[ X]+[ X*@\.&+]+").unwrap();
+    let regex_divider = Regex::new(r"^\*+").unwrap();
+    let regex_header = Regex::new(r"^(Maze|File offset|Size X|Size Y|End|Length):\s*(.*?)\s*$").unwrap();
+
+    let mut levels = Vec::new();
+    let mut level = LegacyLevel { first_line: 0, rows: Vec::new(), metadata: LevelMetadata::default() };
+    for (line_idx, line) in map_contents.lines().enumerate() {
+        if regex_mapcontent.is_match(line) {
+            if level.rows.is_empty() {
+                level.first_line = line_idx + 1;
+            }
+            level.rows.push(line);
+        } else if regex_divider.is_match(line) {
+            if !level.rows.is_empty() {
+                levels.push(level);
+            }
+            level = LegacyLevel { first_line: 0, rows: Vec::new(), metadata: LevelMetadata::default() };
+        } else if let Some(caps) = regex_header.captures(line) {
+            let value = &caps[2];
+            let metadata = &mut level.metadata;
+            match &caps[1] {
+                "Maze" => metadata.maze = value.parse().ok(),
+                "File offset" => metadata.file_offset = Some(value.to_string()),
+                "Size X" => metadata.size_x = value.parse().ok(),
+                "Size Y" => metadata.size_y = value.parse().ok(),
+                "End" => metadata.end = Some(value.to_string()),
+                _ => metadata.length = value.parse().ok(),
+            }
+        }
+    }
+    if !level.rows.is_empty() {
+        levels.push(level);
+    }
+    levels
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::map::{MapCell, MapTile, Position};
+    use crate::validation::ValidationErrorKind;
 
     pub struct FakeMapContentProvider {}
 
@@ -65,10 +390,292 @@ mod tests {
 
     #[test]
     fn test_map_manager() {
-        let mut map_manager = MapManager{ maps : Vec::new()};
+        let mut map_manager = MapManager::new();
         let result = map_manager.read_maps(FakeMapContentProvider{});
         assert!(result.is_ok());
         assert_eq!(2, map_manager.maps.len());
         assert_eq!(1, map_manager.maps[1].id);
+        assert_eq!(1, map_manager.validation_errors_for_map(0).count());
+        assert_eq!(3, map_manager.validation_errors[0].line);
+        assert_eq!(5, map_manager.validation_errors.last().unwrap().line);
+    }
+
+    #[test]
+    fn test_default_maps_are_consistent() {
+        let mut map_manager = MapManager::new();
+        map_manager.read_maps(DefaultMapContentProvider{}).unwrap();
+        let raw_maps = DefaultMapContentProvider{}.get_maps().unwrap();
+        let nof_blocks_on_target: Vec<usize> = raw_maps.split(LEGACY_DIVIDER)
+            .map(|block| block.lines().filter(|line| line.starts_with(' ') || line.starts_with('X')).map(|line| line.matches('&').count()).sum())
+            .collect();
+
+        assert_eq!(60, map_manager.maps.len());
+        assert!(map_manager.validation_errors.is_empty(), "{:?}", map_manager.validation_errors);
+        for (idx, map) in map_manager.maps.iter().enumerate() {
+            let nof_targets = map.map.rows().flatten().filter(|tile| **tile == MapTile::TargetZone).count();
+            assert_eq!(nof_targets, map.movable_blocks.len(), "map {}", idx);
+            assert_eq!(nof_blocks_on_target[idx] as u32, map.movable_blocks_in_final_position, "map {}", idx);
+            assert!(map.movable_blocks_in_final_position < map.movable_blocks.len() as u32, "map {}", idx);
+        }
+    }
+
+    pub struct FakeXsbMapContentProvider {}
+
+    impl MapContentProvider for FakeXsbMapContentProvider {
+        fn get_maps(&self) -> Result<String,io::Error>
+        {
+            Ok(String::from("Title: test\n#####\n#@$.#\n#####\n\n; second\n#####\n#+$*#\n#####\n"))
+        }
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(MapFormat::Legacy, detect_format(&DefaultMapContentProvider{}.get_maps().unwrap()));
+        assert_eq!(MapFormat::Xsb, detect_format(&FakeXsbMapContentProvider{}.get_maps().unwrap()));
+        assert_eq!(MapFormat::Legacy, detect_format("Maze: 1\n#\nSize X: 3\nSize Y: 3\n\nXXX\nX@X\nXXX\n"));
+        assert_eq!(MapFormat::Legacy, detect_format("# note\n\n###\n"));
+    }
+
+    #[test]
+    fn test_read_xsb_maps() {
+        let mut map_manager = MapManager::new();
+        map_manager.read_maps(FakeXsbMapContentProvider{}).unwrap();
+        assert_eq!(2, map_manager.maps.len());
+        assert_eq!(1, map_manager.maps[1].id);
+        assert_eq!(1, map_manager.maps[1].movable_blocks_in_final_position);
+        assert!(map_manager.validation_errors.is_empty(), "{:?}", map_manager.validation_errors);
+        assert_eq!(Some(String::from("test")), map_manager.collections[0].info.title);
+        assert_eq!("test", map_manager.collections[0].display_title());
+    }
+
+    #[test]
+    fn test_read_maps_as_declared_format() {
+        let mut map_manager = MapManager::new();
+        map_manager.read_maps_as(FakeXsbMapContentProvider{}, MapFormat::Legacy).unwrap();
+        assert!(map_manager.maps.is_empty());
+    }
+
+    pub struct ConvertedXsbMapContentProvider {}
+
+    impl MapContentProvider for ConvertedXsbMapContentProvider {
+        fn get_maps(&self) -> Result<String,io::Error>
+        {
+            let legacy = DefaultMapContentProvider{}.get_maps()?;
+            Ok(legacy.lines()
+                .map(|line| if line.starts_with(' ') || line.starts_with('X') {
+                    line.replace('*', "$").replace('&', "*").replace('X', "#")
+                } else {
+                    String::new()
+                })
+                .collect::<Vec<String>>()
+                .join("\n"))
+        }
+    }
+
+    fn without_metadata(maps: Vec<Map>) -> Vec<Map> {
+        maps.into_iter().map(|map| Map { metadata: None, ..map }).collect()
+    }
+
+    #[test]
+    fn test_default_maps_metadata() {
+        let mut map_manager = MapManager::new();
+        map_manager.read_maps(DefaultMapContentProvider{}).unwrap();
+        let metadata = map_manager.maps[0].metadata.clone().unwrap();
+        assert_eq!(LevelMetadata { maze: Some(1), file_offset: Some(String::from("148C, DS:00FC, table offset: 0000")), size_x: Some(22),
+            size_y: Some(11), end: Some(String::from("14BD")), length: Some(50) }, metadata);
+        for map in map_manager.maps.iter() {
+            assert_eq!(map.id + 1, map.display_number());
+        }
+    }
+
+    #[test]
+    fn test_legacy_size_mismatch() {
+        let map_contents = "Maze: 7\nSize X: 6\nSize Y: 3\n\nXXXXX\nX@*.X\nXXXXX\n\n*****\n";
+        let mut map_manager = MapManager::new();
+        map_manager.read_maps(StringMapContentProvider { map_contents: String::from(map_contents) }).unwrap();
+        assert_eq!(7, map_manager.maps[0].display_number());
+        let errors: Vec<(usize, String)> = map_manager.validation_errors.iter().map(|error| (error.line, error.kind.to_string())).collect();
+        assert_eq!(vec![(5, String::from("header declares size 6x3 but level is 5x3"))], errors);
+    }
+
+    #[test]
+    fn test_xsb_maps_equal_legacy_maps() {
+        let mut legacy_manager = MapManager::new();
+        legacy_manager.read_maps(DefaultMapContentProvider{}).unwrap();
+        let mut xsb_manager = MapManager::new();
+        xsb_manager.read_maps(ConvertedXsbMapContentProvider{}).unwrap();
+        assert_eq!(without_metadata(legacy_manager.maps), xsb_manager.maps);
+    }
+
+    #[test]
+    fn test_file_map_content_provider() {
+        let provider = FileMapContentProvider { path: PathBuf::from("data/maps/maps.txt") };
+        assert_eq!(DefaultMapContentProvider{}.get_maps().unwrap(), provider.get_maps().unwrap());
+        assert_eq!("maps", provider.collection_name());
+    }
+
+    #[test]
+    fn test_embedded_map_content_provider() {
+        assert_eq!(DefaultMapContentProvider{}.get_maps().unwrap(), EmbeddedMapContentProvider{}.get_maps().unwrap());
+        assert_eq!("maps", EmbeddedMapContentProvider{}.collection_name());
+    }
+
+    #[test]
+    fn test_collections() {
+        let mut map_manager = MapManager::new();
+        map_manager.read_maps(DefaultMapContentProvider{}).unwrap();
+        map_manager.read_maps(FakeXsbMapContentProvider{}).unwrap();
+        assert_eq!(2, map_manager.collections.len());
+        assert_eq!("maps", map_manager.collections[0].name);
+        assert_eq!(0..60, map_manager.collections[0].map_ids);
+        assert_eq!(60..62, map_manager.collections[1].map_ids);
+        assert_eq!(61, map_manager.maps[61].id);
+        assert_eq!(Some(&map_manager.collections[1]), map_manager.collection_for_map(60));
+        assert_eq!(None, map_manager.collection_for_map(62));
+        assert_eq!("maps", map_manager.collections[0].display_title());
+        assert_eq!(&map_manager.maps[60..], map_manager.maps_of(&map_manager.collections[1]));
+    }
+
+    #[test]
+    fn test_validation_errors_of_later_collection() {
+        let mut map_manager = MapManager::new();
+        map_manager.read_maps(DefaultMapContentProvider{}).unwrap();
+        map_manager.read_maps(StringMapContentProvider { map_contents: String::from("5#|#@$.#|5#\n5#|#@$-#|5#\n") }).unwrap();
+        let error = map_manager.validation_errors_for_map(61).next().unwrap();
+        assert_eq!("level 62, line 2: level has 1 blocks but 0 target zones", error.to_string());
+        assert_eq!(62, map_manager.maps[61].display_number());
+    }
+
+    #[test]
+    fn test_read_single_level_strings() {
+        for map_contents in ["XXXXX\nX@*.X\nXXXXX", "#####\n#@$.#\n#####\n", "5#|#@$.#|5#"] {
+            let mut map_manager = MapManager::new();
+            map_manager.read_maps(StringMapContentProvider { map_contents: String::from(map_contents) }).unwrap();
+            assert_eq!(1, map_manager.maps.len(), "{}", map_contents);
+            assert_eq!(1, map_manager.maps[0].movable_blocks.len(), "{}", map_contents);
+            assert!(map_manager.validation_errors.is_empty(), "{}", map_contents);
+        }
+    }
+
+    #[test]
+    fn test_write_legacy_maps() {
+        let mut map = Map::new();
+        map.parse_map_block(&["XXXX", "X@&X", "XXXX"]);
+        assert_eq!(format!("Maze: 1\nSize X: 4\nSize Y: 3\n\nXXXX\nX@&X\nXXXX\n\n{}\n", LEGACY_DIVIDER), write_legacy_maps(&[map]));
+    }
+
+    #[test]
+    fn test_legacy_round_trip() {
+        let mut map_manager = MapManager::new();
+        map_manager.read_maps(DefaultMapContentProvider{}).unwrap();
+        let mut written_manager = MapManager::new();
+        written_manager.read_maps(StringMapContentProvider { map_contents: write_legacy_maps(&map_manager.maps) }).unwrap();
+        assert_eq!(map_manager.maps, written_manager.maps);
+    }
+
+    #[test]
+    fn test_xsb_round_trip() {
+        let mut map_manager = MapManager::new();
+        map_manager.read_maps(DefaultMapContentProvider{}).unwrap();
+        let mut written_manager = MapManager::new();
+        written_manager.read_maps(StringMapContentProvider { map_contents: xsb::write_maps(&map_manager.maps) }).unwrap();
+        assert_eq!(without_metadata(map_manager.maps), written_manager.maps);
+    }
+
+    #[test]
+    fn test_read_slc_maps() {
+        let slc = "<SokobanLevels><Title>Small</Title>\n<LevelCollection Copyright=\"Jane Doe\">\n\
+            <Level Id=\"A\"><L>#####</L>\n<L>#@$.#</L>\n<L>#####</L></Level>\n\
+            <Level Id=\"B\"><L>#####</L>\n<L>#@$ #</L>\n<L>#####</L></Level>\n\
+            </LevelCollection></SokobanLevels>";
+        let mut map_manager = MapManager::new();
+        map_manager.read_maps(StringMapContentProvider { map_contents: String::from(slc) }).unwrap();
+        assert_eq!(Some(String::from("Small")), map_manager.collections[0].info.title);
+        assert_eq!(Some(String::from("Jane Doe")), map_manager.collections[0].info.copyright);
+        assert_eq!(2, map_manager.maps.len());
+        assert_eq!(1, map_manager.maps[1].id);
+        assert_eq!(Some(String::from("B")), map_manager.maps[1].info.title);
+        assert_eq!(MapCell::Block, map_manager.maps[0].get_cell_for_position(&Position { x: 2, y: 1 }));
+        let errors: Vec<(usize, usize)> = map_manager.validation_errors.iter().map(|error| (error.level_idx, error.line)).collect();
+        assert_eq!(vec![(1, 6)], errors);
+    }
+
+    #[test]
+    fn test_read_slc_maps_size_mismatch() {
+        let slc = "<SokobanLevels><LevelCollection>\n\
+            <Level Id=\"A\" Width=\"5\" Height=\"3\"><L>#####</L>\n<L>#@$.#</L>\n<L>#####</L></Level>\n\
+            <Level Id=\"B\" Width=\"6\" Height=\"3\"><L>#####</L>\n<L>#@$.#</L>\n<L>#####</L></Level>\n\
+            </LevelCollection></SokobanLevels>";
+        let mut map_manager = MapManager::new();
+        map_manager.read_maps(StringMapContentProvider { map_contents: String::from(slc) }).unwrap();
+        assert_eq!(1, map_manager.validation_errors.len());
+        assert_eq!(1, map_manager.validation_errors[0].level_idx);
+        assert_eq!(ValidationErrorKind::SizeMismatch { declared_width: 6, declared_height: 3, width: 5, height: 3 },
+            map_manager.validation_errors[0].kind);
+    }
+
+    #[test]
+    fn test_read_rle_maps() {
+        let mut map_manager = MapManager::new();
+        let map_contents = String::from("5#|#@$.#|5#\n\n5#|#@$-#|5#\n");
+        map_manager.read_maps(StringMapContentProvider { map_contents }).unwrap();
+        assert_eq!(2, map_manager.maps.len());
+        assert_eq!(vec!["#####", "#@$.#", "#####"], map_manager.maps[0].write_rows(MapFormat::Xsb));
+        assert_eq!(1, map_manager.maps[1].id);
+        let errors: Vec<(usize, usize)> = map_manager.validation_errors.iter().map(|error| (error.level_idx, error.line)).collect();
+        assert_eq!(vec![(1, 3)], errors);
+    }
+
+    #[test]
+    fn test_empty_levels_are_skipped_or_rejected() {
+        let mut map_manager = MapManager::new();
+        let map_contents = format!("{0}
+{0}
+ XX
+{0}
+
+{0}
+", LEGACY_DIVIDER);
+        map_manager.read_maps_as(StringMapContentProvider { map_contents }, MapFormat::Legacy).unwrap();
+        assert_eq!(1, map_manager.maps.len());
+        let result = map_manager.read_maps(StringMapContentProvider { map_contents: String::from("|") });
+        assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+        assert_eq!(1, map_manager.maps.len());
+    }
+
+    #[test]
+    fn test_read_invalid_rle_maps() {
+        let mut map_manager = MapManager::new();
+        let result = map_manager.read_rle_maps(StringMapContentProvider { map_contents: String::from("5#|#@X.#|5#") });
+        assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+        assert!(map_manager.collections.is_empty());
+    }
+
+    #[test]
+    fn test_failed_read_keeps_previous_collections() {
+        let mut map_manager = MapManager::new();
+        map_manager.read_maps(FakeXsbMapContentProvider{}).unwrap();
+        let before = (map_manager.maps.clone(), map_manager.collections.clone(), map_manager.validation_errors.clone());
+        let map_contents = String::from("5#|#@$.#|5#\n5#|#@X.#|5#\n");
+        assert!(map_manager.read_rle_maps(StringMapContentProvider { map_contents }).is_err());
+        assert!(map_manager.read_maps(StringMapContentProvider { map_contents: String::from("<LevelCollection") }).is_err());
+        assert_eq!(before, (map_manager.maps, map_manager.collections, map_manager.validation_errors));
+    }
+
+    #[test]
+    fn test_rle_round_trip() {
+        let mut map_manager = MapManager::new();
+        map_manager.read_maps(DefaultMapContentProvider{}).unwrap();
+        let map_contents = map_manager.maps.iter().map(|map| format!("{}\n", rle::encode(map))).collect();
+        let mut written_manager = MapManager::new();
+        written_manager.read_maps(StringMapContentProvider { map_contents }).unwrap();
+        for (map, written_map) in map_manager.maps.iter().zip(written_manager.maps.iter()) {
+            assert_eq!(map.write_rows(MapFormat::Xsb).iter().map(|row| row.trim_end()).collect::<Vec<&str>>(),
+                written_map.write_rows(MapFormat::Xsb).iter().map(|row| row.trim_end()).collect::<Vec<&str>>());
+            assert_eq!(map.movable_blocks, written_map.movable_blocks);
+            assert_eq!(map.player_position, written_map.player_position);
+        }
+        assert_eq!(60, written_manager.maps.len());
+        assert!(written_manager.validation_errors.is_empty());
     }
 }
@@ -1,13 +1,82 @@
 use crate::{Map, MapTile, Position};
 
-#[derive(Debug, Eq, PartialEq)]
+/// Direction of a single player step.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum MoveDirection {
+    /// One row up.
     Up,
+    /// One row down.
     Down,
+    /// One column to the left.
     Left,
+    /// One column to the right.
     Right,
 }
 
+impl MoveDirection {
+    /// All four directions.
+    pub const ALL: [MoveDirection; 4] = [MoveDirection::Up, MoveDirection::Down, MoveDirection::Left, MoveDirection::Right];
+
+    /// Returns the direction pointing the other way.
+    pub fn opposite(&self) -> MoveDirection {
+        match self {
+            MoveDirection::Up => MoveDirection::Down,
+            MoveDirection::Down => MoveDirection::Up,
+            MoveDirection::Left => MoveDirection::Right,
+            MoveDirection::Right => MoveDirection::Left,
+        }
+    }
+
+    /// Returns the LURD character of a step into this direction, uppercase for pushes.
+    pub fn to_lurd(self, is_push: bool) -> char {
+        let c = match self {
+            MoveDirection::Up => 'u',
+            MoveDirection::Down => 'd',
+            MoveDirection::Left => 'l',
+            MoveDirection::Right => 'r',
+        };
+        if is_push { c.to_ascii_uppercase() } else { c }
+    }
+}
+
+/// Result of [`apply_move`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MoveOutcome {
+    /// The step was not possible, the map stays as it was.
+    Blocked,
+    /// The player walked without pushing a block.
+    Walked,
+    /// The player pushed the block with index `block` from `from` to `to`.
+    Pushed {
+        /// Index into [`Map::movable_blocks`].
+        block: usize,
+        /// Position of the block before the push.
+        from: Position,
+        /// Position of the block after the push.
+        to: Position,
+    },
+    /// Like [`MoveOutcome::Pushed`], and the push solved the level.
+    Solved {
+        /// Index into [`Map::movable_blocks`].
+        block: usize,
+        /// Position of the block before the push.
+        from: Position,
+        /// Position of the block after the push.
+        to: Position,
+    },
+}
+
+impl MoveOutcome {
+    /// Returns the index of the pushed block, if any.
+    pub fn pushed_block(&self) -> Option<usize> {
+        match self {
+            MoveOutcome::Pushed { block, .. } | MoveOutcome::Solved { block, .. } => Some(*block),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the position one step from `current_position` into `dir`.
 pub fn calc_new_position_after_movement(dir: &MoveDirection, current_position: &Position) -> Position {
     let mut dx: i32 = 0;
     let mut dy: i32 = 0;
@@ -19,33 +88,86 @@ pub fn calc_new_position_after_movement(dir: &MoveDirection, current_position: &
         MoveDirection::Right => dx = 1,
     }
 
-    let new_position = Position {
+    Position {
         x: current_position.x + dx,
         y: current_position.y + dy,
-    };
-    return new_position;
+    }
 }
 
+/// Returns whether the player may step onto `target_position` walking into `dir`.
+///
+/// A block on `target_position` can be pushed if the tile behind it is free.
+/// `probing_block` is set while checking that tile, since blocks cannot push other blocks.
 pub fn can_move_to(current_map: &Map, target_position: &Position, dir: &MoveDirection, probing_block: bool) -> bool {
-    let mut tile_type = current_map.get_tile_type_for_position(target_position);
     if current_map.is_movable_block_at(target_position) {
-        tile_type = MapTile::Block;
+        if probing_block {
+            return false;
+        }
+        let new_target_position = calc_new_position_after_movement(dir, target_position);
+
+        return can_move_to(current_map, &new_target_position, dir, true);
     }
-    match tile_type {
-        MapTile::Space | MapTile::TargetZone => return true,
-        MapTile::Block => {
-            if probing_block {
-                return false;
-            }
-            let new_target_position = calc_new_position_after_movement(&dir, target_position);
+    matches!(current_map.get_tile_type_for_position(target_position), MapTile::Space | MapTile::TargetZone)
+}
 
-            if can_move_to(current_map, &new_target_position, dir, true) {
-                return true;
+/// Applies a player step into `dir` to `map` and returns the resulting map together with what happened.
+///
+/// `map` itself is left untouched. In the returned map the player and the pushed block have moved
+/// and [`Map::movable_blocks_in_final_position`] is updated, on [`MoveOutcome::Blocked`] it equals `map`.
+pub fn apply_move(map: &Map, dir: MoveDirection) -> (Map, MoveOutcome) {
+    let mut next = map.clone();
+    let outcome = apply_move_in_place(&mut next, dir);
+    (next, outcome)
+}
+
+/// Like [`apply_move`], but changes `map` in place. It is the single implementation of the movement
+/// rules, used by [`LevelSession`](crate::LevelSession) which keeps the live map, [`undo_move`] takes a step back.
+pub(crate) fn apply_move_in_place(map: &mut Map, dir: MoveDirection) -> MoveOutcome {
+    let new_pos = calc_new_position_after_movement(&dir, &map.player_position);
+    if !can_move_to(map, &new_pos, &dir, false) {
+        return MoveOutcome::Blocked;
+    }
+    let pushed_block = map.get_movable_block_index_at(&new_pos);
+    move_player(map, dir, pushed_block);
+    match pushed_block {
+        Some(block) => {
+            let to = map.movable_blocks[block].position;
+            if map.is_solved() {
+                MoveOutcome::Solved { block, from: new_pos, to }
             } else {
-                return false;
+                MoveOutcome::Pushed { block, from: new_pos, to }
             }
         }
-        _ => return false,
+        None => MoveOutcome::Walked,
+    }
+}
+
+/// Takes back a step into `dir` applied by [`apply_move_in_place`], pulling the block with index `pushed_block`
+/// back along if the step pushed it.
+pub(crate) fn undo_move(map: &mut Map, dir: MoveDirection, pushed_block: Option<usize>) {
+    move_player(map, dir.opposite(), pushed_block);
+}
+
+/// Moves the player and the given block one step into `dir`, without checking the rules.
+fn move_player(map: &mut Map, dir: MoveDirection, block: Option<usize>) {
+    map.player_position = calc_new_position_after_movement(&dir, &map.player_position);
+    if let Some(block) = block {
+        let from = map.movable_blocks[block].position;
+        map.movable_blocks[block].move_to(&dir);
+        let to = map.movable_blocks[block].position;
+        update_blocks_in_final_position(map, &from, &to);
+    }
+}
+
+fn update_blocks_in_final_position(map: &mut Map, old_position: &Position, new_position: &Position) {
+    if map.get_tile_type_for_position(new_position) == MapTile::TargetZone && map.get_tile_type_for_position(old_position) != MapTile::TargetZone
+    {
+        map.movable_blocks_in_final_position += 1;
+    }
+
+    if map.get_tile_type_for_position(new_position) != MapTile::TargetZone && map.get_tile_type_for_position(old_position) == MapTile::TargetZone
+    {
+        map.movable_blocks_in_final_position -= 1;
     }
 }
 
@@ -68,6 +190,7 @@ mod tests {
 
     use super::*;
     use crate::map::MovableBlock;
+    use crate::test_util::parse;
     calc_new_position_after_movement_tests! {
         test_up: (MoveDirection::Up, Position{x : 5, y : 4}),
         test_down: (MoveDirection::Down, Position{x : 5, y : 6}),
@@ -75,39 +198,146 @@ mod tests {
         test_right: (MoveDirection::Right, Position{x : 6, y : 5}),
     }
 
+    #[test]
+    fn test_opposite() {
+        assert_eq!(MoveDirection::Down, MoveDirection::Up.opposite());
+        assert_eq!(MoveDirection::Up, MoveDirection::Down.opposite());
+        assert_eq!(MoveDirection::Right, MoveDirection::Left.opposite());
+        assert_eq!(MoveDirection::Left, MoveDirection::Right.opposite());
+    }
+
+    #[test]
+    fn test_to_lurd() {
+        assert_eq!('l', MoveDirection::Left.to_lurd(false));
+        assert_eq!('U', MoveDirection::Up.to_lurd(true));
+        assert_eq!('r', MoveDirection::Right.to_lurd(false));
+        assert_eq!('D', MoveDirection::Down.to_lurd(true));
+    }
+
     #[test]
     fn test_can_move_to() {
-        let map = Map::new();
+        let map = Map::with_size(3, 1);
         assert!(can_move_to(&map, &Position { x: 1, y: 0}, &MoveDirection::Right, false));    
     }
    
     #[test]
     fn test_can_not_move_to() {
-        let mut map = Map::new();
-        map.map[0][1] = MapTile::Wall;
+        let mut map = Map::with_size(3, 1);
+        map.map.set(1, 0, MapTile::Wall);
         assert!(!can_move_to(&map, &Position { x: 1, y: 0}, &MoveDirection::Right, false));    
     }
 
     #[test]
     fn test_can_move_block() {
-        let mut map = Map::new();
+        let mut map = Map::with_size(3, 1);
         map.movable_blocks.push(MovableBlock { position: Position {x: 1, y: 0} });
         assert!(can_move_to(&map, &Position { x: 1, y: 0}, &MoveDirection::Right, false));    
     }
    
     #[test]
     fn test_cannot_move_block_in_wall() {
-        let mut map = Map::new();
-        map.map[0][2] = MapTile::Wall;
+        let mut map = Map::with_size(3, 1);
+        map.map.set(2, 0, MapTile::Wall);
         map.movable_blocks.push(MovableBlock { position: Position {x: 1, y: 0} });
         assert!(!can_move_to(&map, &Position { x: 1, y: 0}, &MoveDirection::Right, false));    
     }
 
+    #[test]
+    fn test_cannot_move_outside_of_map() {
+        let map = Map::with_size(3, 1);
+        assert!(!can_move_to(&map, &Position { x: 3, y: 0}, &MoveDirection::Right, false));
+        assert!(!can_move_to(&map, &Position { x: 0, y: -1}, &MoveDirection::Up, false));
+    }
+
+    #[test]
+    fn test_cannot_push_block_outside_of_map() {
+        let mut map = Map::with_size(3, 1);
+        map.movable_blocks.push(MovableBlock { position: Position {x: 0, y: 0} });
+        assert!(!can_move_to(&map, &Position { x: 0, y: 0}, &MoveDirection::Left, false));
+    }
+
     #[test]
     fn test_cannot_move_block_in_block() {
-        let mut map = Map::new();
+        let mut map = Map::with_size(4, 1);
         map.movable_blocks.push(MovableBlock { position: Position {x: 1, y: 0} });
         map.movable_blocks.push(MovableBlock { position: Position {x: 2, y: 0} });
         assert!(!can_move_to(&map, &Position { x: 1, y: 0}, &MoveDirection::Right, false));    
     }
+
+    fn setup_target_zone_map() -> Map {
+        let mut map = Map::with_size(2, 1);
+        map.map.set(0, 0, MapTile::TargetZone);
+        map
+    }
+
+    #[test]
+    fn test_move_box_into_target_zone() {
+        let mut map = setup_target_zone_map();
+        update_blocks_in_final_position(&mut map, &Position {x: 1, y: 0}, &Position {x: 0, y: 0});
+        assert_eq!(1, map.movable_blocks_in_final_position);
+    }
+
+    #[test]
+    fn test_move_box_out_of_target_zone() {
+        let mut map = setup_target_zone_map();
+        map.movable_blocks_in_final_position = 1;
+        update_blocks_in_final_position(&mut map, &Position {x: 0, y: 0}, &Position {x: 1, y: 0});
+        assert_eq!(0, map.movable_blocks_in_final_position);
+    }
+
+    #[test]
+    fn test_move_box_in_target_zone() {
+        let mut map = setup_target_zone_map();
+        map.movable_blocks_in_final_position = 1;
+        map.map.set(1, 0, MapTile::TargetZone);
+        update_blocks_in_final_position(&mut map, &Position {x: 0, y: 0}, &Position {x: 1, y: 0});
+        assert_eq!(1, map.movable_blocks_in_final_position);
+    }
+
+    #[test]
+    fn test_apply_move_blocked() {
+        let map = parse(&["X@**.X"]);
+        assert_eq!((map.clone(), MoveOutcome::Blocked), apply_move(&map, MoveDirection::Left));
+        assert_eq!((map.clone(), MoveOutcome::Blocked), apply_move(&map, MoveDirection::Right));
+    }
+
+    #[test]
+    fn test_apply_move_walked() {
+        let map = parse(&["X @*. X"]);
+        let (next, outcome) = apply_move(&map, MoveDirection::Left);
+        assert_eq!(MoveOutcome::Walked, outcome);
+        assert_eq!(Position { x: 1, y: 0 }, next.player_position);
+        assert_eq!(Position { x: 2, y: 0 }, map.player_position);
+    }
+
+    #[test]
+    fn test_apply_move_pushed() {
+        let map = parse(&["X@*. .*X"]);
+        let (next, outcome) = apply_move(&map, MoveDirection::Right);
+        assert_eq!(MoveOutcome::Pushed { block: 0, from: Position { x: 2, y: 0 }, to: Position { x: 3, y: 0 } }, outcome);
+        assert_eq!(Some(0), outcome.pushed_block());
+        assert_eq!(Position { x: 2, y: 0 }, next.player_position);
+        assert_eq!(1, next.movable_blocks_in_final_position);
+        assert_eq!(parse(&["X@*. .*X"]), map);
+    }
+
+    #[test]
+    fn test_apply_move_solved() {
+        let (next, outcome) = apply_move(&parse(&["X@*.X"]), MoveDirection::Right);
+        assert_eq!(MoveOutcome::Solved { block: 0, from: Position { x: 2, y: 0 }, to: Position { x: 3, y: 0 } }, outcome);
+        assert!(next.is_solved());
+    }
+
+    #[test]
+    fn test_undo_move() {
+        let mut map = parse(&["X @*. X"]);
+        let start = map.clone();
+        let outcome = apply_move_in_place(&mut map, MoveDirection::Right);
+        undo_move(&mut map, MoveDirection::Right, outcome.pushed_block());
+        assert_eq!(start, map);
+
+        apply_move_in_place(&mut map, MoveDirection::Left);
+        undo_move(&mut map, MoveDirection::Left, None);
+        assert_eq!(start, map);
+    }
 }
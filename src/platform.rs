@@ -1,8 +1,8 @@
 use crate::{Draw, TerminalDrawer, input::UserInputProvider, input::TerminalInput};
 
 pub struct PlatformSpecific {
-    pub renderer: Box<(dyn Draw)>,
-    pub input_provider: Box<(dyn UserInputProvider)>
+    pub renderer: Box<dyn Draw>,
+    pub input_provider: Box<dyn UserInputProvider>
 }
 
 impl PlatformSpecific {
@@ -0,0 +1,146 @@
+use std::io;
+
+use regex::Regex;
+
+use crate::map::{Map, MapFormat};
+
+/// Upper limit for the length of a decoded level, including row separators.
+const MAX_DECODED_LEN: usize = 1 << 16;
+
+/// Returns whether `line` is a level in run-length encoded XSB notation.
+///
+/// Besides the XSB characters such a line may only contain run lengths, `-`/`_` for floor,
+/// `|` as row separator and parentheses for repeated groups, and it needs at least
+/// one row separator or run length. `regex_rle` comes from [`regex_rle`].
+pub fn is_rle(regex_rle: &Regex, line: &str) -> bool {
+    let line = line.trim();
+    regex_rle.is_match(line) && line.contains(|c: char| c == '|' || c.is_ascii_digit())
+}
+
+/// Compiles the pattern matching the characters of run-length encoded levels, see [`is_rle`].
+pub fn regex_rle() -> Regex {
+    Regex::new(r"^[0-9#@+$*.\-_ |()]+$").unwrap()
+}
+
+/// Decodes a run-length encoded level into its XSB rows, e.g. `4#|#@$.#` into `####` and `#@$.#`.
+pub fn decode(line: &str) -> Result<Vec<String>, io::Error> {
+    let mut chars = line.trim().chars().peekable();
+    let expanded = expand(&mut chars, false)?;
+    if expanded.chars().all(|c| matches!(c, '-' | '_' | ' ' | '|')) {
+        return Err(invalid_data(String::from("empty RLE level")));
+    }
+    Ok(expanded.split('|').map(|row| row.replace(['-', '_'], " ")).collect())
+}
+
+fn expand(chars: &mut std::iter::Peekable<std::str::Chars>, in_group: bool) -> Result<String, io::Error> {
+    let mut expanded = String::new();
+    let mut count: Option<usize> = None;
+    while let Some(c) = chars.next() {
+        let part = match c {
+            '0'..='9' => {
+                let digit = c.to_digit(10).unwrap() as usize;
+                count = Some(count.unwrap_or(0).checked_mul(10).and_then(|count| count.checked_add(digit))
+                    .filter(|count| *count <= MAX_DECODED_LEN)
+                    .ok_or_else(|| invalid_data(String::from("run length too large in RLE level")))?);
+                continue;
+            }
+            '(' => expand(chars, true)?,
+            ')' if in_group => return check_no_count(count).map(|_| expanded),
+            '#' | '@' | '+' | '$' | '*' | '.' | '-' | '_' | ' ' | '|' => c.to_string(),
+            _ => return Err(invalid_data(format!("unexpected character '{}' in RLE level", c))),
+        };
+        let repeat = count.take().unwrap_or(1);
+        if expanded.len() + part.len() * repeat > MAX_DECODED_LEN {
+            return Err(invalid_data(String::from("RLE level too large")));
+        }
+        expanded += &part.repeat(repeat);
+    }
+    check_no_count(count)?;
+    if in_group {
+        return Err(invalid_data(String::from("unclosed group in RLE level")));
+    }
+    Ok(expanded)
+}
+
+fn check_no_count(count: Option<usize>) -> Result<(), io::Error> {
+    match count {
+        Some(count) => Err(invalid_data(format!("run length {} without character in RLE level", count))),
+        None => Ok(()),
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Encodes a level as run-length encoded XSB string with `-` for floor and `|` between rows.
+pub fn encode(map: &Map) -> String {
+    map.write_rows(MapFormat::Xsb)
+        .iter()
+        .map(|row| encode_row(row.trim_end()))
+        .collect::<Vec<String>>()
+        .join("|")
+}
+
+fn encode_row(row: &str) -> String {
+    let mut encoded = String::new();
+    let mut chars = row.chars().map(|c| if c == ' ' { '-' } else { c }).peekable();
+    while let Some(c) = chars.next() {
+        let mut count = 1;
+        while chars.peek() == Some(&c) {
+            chars.next();
+            count += 1;
+        }
+        if count > 1 {
+            encoded += &count.to_string();
+        }
+        encoded.push(c);
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_rle() {
+        let regex_rle = regex_rle();
+        assert!(is_rle(&regex_rle, "4#|#@$.#"));
+        assert!(is_rle(&regex_rle, "3(#-)|#@$.#\n"));
+        assert!(!is_rle(&regex_rle, "#@$.#"));
+        assert!(!is_rle(&regex_rle, "XXXXX"));
+        assert!(!is_rle(&regex_rle, "Title: 4#|#@$.#"));
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(vec!["####", "#@$.#"], decode("4#|#@$.#").unwrap());
+        assert_eq!(vec!["#  #", "# # # #"], decode("#2-#|3(#_)#").unwrap());
+        assert_eq!(vec!["", "#"], decode("|#").unwrap());
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert_eq!(io::ErrorKind::InvalidData, decode("4#|#@X.#").unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidData, decode("2(#-").unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidData, decode("#|#@$.#3").unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidData, decode("2(#-3)").unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidData, decode("99999999999999999999999#").unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidData, decode("70000#").unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidData, decode("300(300(#))").unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidData, decode("|").unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidData, decode("3|2-").unwrap_err().kind());
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut map = Map::new();
+        map.parse_map_block(&["  XXXXX", "XXX  @X", "X&*  .X", "XXXXXXX"]);
+        assert_eq!("2-5#|3#2-@#|#*$2-.#|7#", encode(&map));
+        let mut decoded = Map::new();
+        let rows = decode(&encode(&map)).unwrap();
+        decoded.parse_map_block_with_format(&rows.iter().map(String::as_str).collect::<Vec<&str>>(), MapFormat::Xsb);
+        assert_eq!(map.write_rows(MapFormat::Xsb), decoded.write_rows(MapFormat::Xsb));
+    }
+}
@@ -0,0 +1,320 @@
+use std::time::{Duration, Instant};
+
+use crate::{Map, MoveDirection};
+use crate::movement::{self, MoveOutcome};
+
+/// Progress of a [`LevelSession`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SessionState {
+    /// The level is not solved yet.
+    Playing,
+    /// All blocks are on a target zone.
+    Won,
+}
+
+/// A single accepted player step.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Step {
+    /// Direction the player walked.
+    pub direction: MoveDirection,
+    /// Index into [`Map::movable_blocks`] of the pushed block, if any.
+    pub pushed_block: Option<usize>,
+}
+
+impl Step {
+    /// Returns whether a block was pushed.
+    pub fn is_push(self) -> bool {
+        self.pushed_block.is_some()
+    }
+
+    /// Returns the LURD character of this step.
+    pub fn to_lurd(self) -> char {
+        self.direction.to_lurd(self.is_push())
+    }
+}
+
+/// Result of a level attempt.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct LevelScore {
+    /// Number of steps, including pushes.
+    pub moves: usize,
+    /// Number of steps which pushed a block.
+    pub pushes: usize,
+    /// Time spent on the level.
+    pub time: Duration,
+}
+
+impl LevelScore {
+    /// Compares by moves first, then pushes and time.
+    pub fn is_better_than(&self, other: &LevelScore) -> bool {
+        (self.moves, self.pushes, self.time) < (other.moves, other.pushes, other.time)
+    }
+}
+
+/// The state of the level being played: the live map, the starting map and the move history.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LevelSession {
+    /// The live map the player moves on.
+    pub map: Map,
+    initial_map: Map,
+    /// Steps taken since the start of the level.
+    pub history: Vec<Step>,
+    redo_stack: Vec<Step>,
+    state: SessionState,
+    started_at: Instant,
+    completed_in: Option<Duration>,
+}
+
+impl LevelSession {
+    /// Starts a session on `map`.
+    pub fn new(map: Map) -> LevelSession {
+        LevelSession { map: map.clone(), initial_map: map, history: Vec::new(), redo_stack: Vec::new(), state: SessionState::Playing,
+            started_at: Instant::now(), completed_in: None }
+    }
+
+    /// Restores the starting map and clears the history.
+    pub fn restart(&mut self) {
+        self.map = self.initial_map.clone();
+        self.history.clear();
+        self.redo_stack.clear();
+        self.state = SessionState::Playing;
+        self.started_at = Instant::now();
+        self.completed_in = None;
+    }
+
+    /// Replaces the session with a new one on `map`.
+    pub fn switch_level(&mut self, map: Map) {
+        *self = LevelSession::new(map);
+    }
+
+    /// Marks the level as solved and stops the clock.
+    pub fn win(&mut self) {
+        self.state = SessionState::Won;
+        self.completed_in = Some(self.started_at.elapsed());
+    }
+
+    /// Records a step already applied to the live map and discards the redo branch.
+    pub fn record_step(&mut self, step: Step) {
+        self.history.push(step);
+        self.redo_stack.clear();
+    }
+
+    /// Applies a player step to the live map, records it and marks the level as won once solved.
+    pub fn apply_move(&mut self, movedir: MoveDirection) -> MoveOutcome {
+        let outcome = movement::apply_move_in_place(&mut self.map, movedir);
+        if outcome != MoveOutcome::Blocked {
+            self.record_step(Step { direction: movedir, pushed_block: outcome.pushed_block() });
+        }
+        if let MoveOutcome::Solved { .. } = outcome {
+            self.win();
+        }
+        outcome
+    }
+
+    /// Takes back the last step. Returns `false` if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(step) => {
+                movement::undo_move(&mut self.map, step.direction, step.pushed_block);
+                self.redo_stack.push(step);
+                self.state = SessionState::Playing;
+                self.completed_in = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replays the last undone step and marks the level as won once solved.
+    /// Returns `false` if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.redo_stack.pop() {
+            Some(step) => {
+                if let MoveOutcome::Solved { .. } = movement::apply_move_in_place(&mut self.map, step.direction) {
+                    self.win();
+                }
+                self.history.push(step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Number of steps taken.
+    pub fn nof_moves(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Number of steps which pushed a block.
+    pub fn nof_pushes(&self) -> usize {
+        self.history.iter().filter(|step| step.is_push()).count()
+    }
+
+    /// The history as LURD string, lowercase for moves and uppercase for pushes.
+    pub fn lurd(&self) -> String {
+        self.history.iter().map(|step| step.to_lurd()).collect()
+    }
+
+    /// Time spent on the level, frozen once it is solved.
+    pub fn elapsed(&self) -> Duration {
+        self.completed_in.unwrap_or_else(|| self.started_at.elapsed())
+    }
+
+    /// Current moves, pushes and time.
+    pub fn score(&self) -> LevelScore {
+        LevelScore { moves: self.nof_moves(), pushes: self.nof_pushes(), time: self.elapsed() }
+    }
+
+    /// Returns whether the level has been solved.
+    pub fn is_won(&self) -> bool {
+        self.state == SessionState::Won
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    fn setup_session() -> LevelSession {
+        let mut map = Map::new();
+        map.parse_map_block(&["X@*.X"]);
+        LevelSession::new(map)
+    }
+
+    fn push_right(session: &mut LevelSession) {
+        session.map.player_position = Position { x: 2, y: 0 };
+        session.map.movable_blocks[0].move_to(&MoveDirection::Right);
+        session.map.update_movable_blocks_in_final_position();
+        session.record_step(Step { direction: MoveDirection::Right, pushed_block: Some(0) });
+    }
+
+    #[test]
+    fn test_new_session() {
+        let session = setup_session();
+        assert_eq!(session.initial_map, session.map);
+        assert!(session.history.is_empty());
+        assert_eq!(SessionState::Playing, session.state);
+    }
+
+    #[test]
+    fn test_restart() {
+        let mut session = setup_session();
+        push_right(&mut session);
+        session.win();
+        session.restart();
+        assert_eq!(session.initial_map, session.map);
+        assert!(session.history.is_empty());
+        assert!(!session.redo());
+        assert!(!session.is_won());
+    }
+
+    #[test]
+    fn test_switch_level() {
+        let mut session = setup_session();
+        push_right(&mut session);
+        let mut other_map = Map::new();
+        other_map.id = 1;
+        session.switch_level(other_map.clone());
+        assert_eq!(other_map, session.initial_map);
+        assert_eq!(other_map, session.map);
+        assert!(session.history.is_empty());
+    }
+
+    #[test]
+    fn test_win() {
+        let mut session = setup_session();
+        session.win();
+        assert_eq!(SessionState::Won, session.state);
+    }
+
+    #[test]
+    fn test_undo_push() {
+        let mut session = setup_session();
+        push_right(&mut session);
+        session.win();
+        assert!(session.undo());
+        assert_eq!(session.initial_map, session.map);
+        assert!(session.history.is_empty());
+        assert!(!session.is_won());
+        assert!(!session.undo());
+    }
+
+    #[test]
+    fn test_redo_push() {
+        let mut session = setup_session();
+        push_right(&mut session);
+        let pushed_map = session.map.clone();
+        session.undo();
+        assert!(session.redo());
+        assert_eq!(pushed_map, session.map);
+        assert_eq!(1, session.map.movable_blocks_in_final_position);
+        assert_eq!(vec![Step { direction: MoveDirection::Right, pushed_block: Some(0) }], session.history);
+        assert!(!session.redo());
+    }
+
+    #[test]
+    fn test_counters_and_lurd() {
+        let mut session = setup_session();
+        push_right(&mut session);
+        session.map.player_position = Position { x: 1, y: 0 };
+        session.record_step(Step { direction: MoveDirection::Left, pushed_block: None });
+        assert_eq!(2, session.nof_moves());
+        assert_eq!(1, session.nof_pushes());
+        assert_eq!("Rl", session.lurd());
+        session.undo();
+        assert_eq!(1, session.nof_moves());
+        assert_eq!("R", session.lurd());
+    }
+
+    #[test]
+    fn test_score() {
+        let mut session = setup_session();
+        push_right(&mut session);
+        session.win();
+        let score = session.score();
+        assert_eq!(1, score.moves);
+        assert_eq!(1, score.pushes);
+        assert_eq!(score, session.score());
+    }
+
+    #[test]
+    fn test_score_is_better_than() {
+        let score = LevelScore { moves: 10, pushes: 4, time: Duration::from_secs(30) };
+        assert!(LevelScore { moves: 9, pushes: 5, ..score }.is_better_than(&score));
+        assert!(LevelScore { pushes: 3, ..score }.is_better_than(&score));
+        assert!(LevelScore { time: Duration::from_secs(20), ..score }.is_better_than(&score));
+        assert!(!score.is_better_than(&score));
+        assert!(!LevelScore { moves: 11, pushes: 0, ..score }.is_better_than(&score));
+    }
+
+    #[test]
+    fn test_apply_move() {
+        let mut session = setup_session();
+        assert_eq!(MoveOutcome::Blocked, session.apply_move(MoveDirection::Left));
+        assert!(session.history.is_empty());
+        assert!(matches!(session.apply_move(MoveDirection::Right), MoveOutcome::Solved { block: 0, .. }));
+        assert_eq!("R", session.lurd());
+        assert!(session.is_won());
+    }
+
+    #[test]
+    fn test_redo_solves_level() {
+        let mut session = setup_session();
+        session.apply_move(MoveDirection::Right);
+        session.undo();
+        assert!(!session.is_won());
+        session.redo();
+        assert!(session.is_won());
+    }
+
+    #[test]
+    fn test_new_step_discards_redo() {
+        let mut session = setup_session();
+        push_right(&mut session);
+        session.undo();
+        session.map.player_position = Position { x: 0, y: 0 };
+        session.record_step(Step { direction: MoveDirection::Left, pushed_block: None });
+        assert!(!session.redo());
+    }
+}
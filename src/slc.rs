@@ -0,0 +1,174 @@
+use std::io;
+
+use regex::Regex;
+
+use crate::info::{CollectionInfo, LevelInfo};
+
+/// A level of an SLC collection, its rows are in XSB notation.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SlcLevel<'a> {
+    /// Line number of the first `<L>` row, starting at 1.
+    pub first_line: usize,
+    /// Contents of the `<L>` rows.
+    pub rows: Vec<&'a str>,
+    /// The `Id` of the level as title, its `Copyright` and the optional author, comment and difficulty.
+    pub info: LevelInfo,
+    /// The `Width` attribute of the level.
+    pub width: Option<usize>,
+    /// The `Height` attribute of the level.
+    pub height: Option<usize>,
+}
+
+/// Contents of an SLC file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SlcCollection<'a> {
+    /// Title, description, author and copyright of the collection.
+    pub info: CollectionInfo,
+    /// The levels in file order.
+    pub levels: Vec<SlcLevel<'a>>,
+}
+
+/// Returns whether `map_contents` is an SLC (XML) collection.
+pub fn is_slc(map_contents: &str) -> bool {
+    map_contents.contains("<LevelCollection")
+}
+
+/// Parses an SLC collection.
+///
+/// The collection `<Title>`, `<Description>` and optional `<Author>` elements and the
+/// `Copyright` attribute of `<LevelCollection>` are kept, as well as the `Id`, `Copyright`, `Width`
+/// and `Height` attributes of each `<Level>` and its optional `<Author>`, `<Comment>` and `<Difficulty>` elements.
+pub fn parse(map_contents: &str) -> Result<SlcCollection<'_>, io::Error> {
+    let regex_collection = Regex::new(r"(?s)<LevelCollection\b([^>]*)>(.*)</LevelCollection>").unwrap();
+    let regex_level = Regex::new(r"(?s)<Level\b([^>]*?)(?:/>|>(.*?)</Level>)").unwrap();
+    let regex_row = Regex::new(r"<L>([^<]*)</L>|<L/>").unwrap();
+
+    let collection = regex_collection.captures(map_contents)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no <LevelCollection> element found"))?;
+    let header = &map_contents[..collection.get(0).unwrap().start()];
+    let info = CollectionInfo {
+        title: element_text(header, "Title"),
+        description: element_text(header, "Description"),
+        author: element_text(header, "Author"),
+        copyright: attribute(&collection[1], "Copyright"),
+    };
+
+    let body = collection.get(2).unwrap();
+    let mut levels = Vec::new();
+    for level in regex_level.captures_iter(body.as_str()) {
+        let mut first_line = 0;
+        let mut rows = Vec::new();
+        if let Some(level_body) = level.get(2) {
+            for row in regex_row.captures_iter(level_body.as_str()) {
+                if rows.is_empty() {
+                    let offset = body.start() + level_body.start() + row.get(0).unwrap().start();
+                    first_line = map_contents[..offset].matches('\n').count() + 1;
+                }
+                rows.push(row.get(1).map_or("", |row| row.as_str()));
+            }
+        }
+        let level_body = level.get(2).map_or("", |level_body| level_body.as_str());
+        let info = LevelInfo {
+            title: attribute(&level[1], "Id"),
+            author: element_text(level_body, "Author"),
+            copyright: attribute(&level[1], "Copyright"),
+            comment: element_text(level_body, "Comment"),
+            difficulty: element_text(level_body, "Difficulty"),
+        };
+        let width = attribute(&level[1], "Width").and_then(|width| width.parse().ok());
+        let height = attribute(&level[1], "Height").and_then(|height| height.parse().ok());
+        levels.push(SlcLevel { first_line, rows, info, width, height });
+    }
+    Ok(SlcCollection { info, levels })
+}
+
+fn element_text(xml: &str, name: &str) -> Option<String> {
+    let regex_element = Regex::new(&format!(r"(?s)<{}>(.*?)</{}>", name, name)).unwrap();
+    regex_element.captures(xml).map(|element| unescape(element[1].trim()))
+}
+
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let regex_attribute = Regex::new(&format!(r#"\b{}\s*=\s*(?:"([^"]*)"|'([^']*)')"#, name)).unwrap();
+    regex_attribute.captures(attributes)
+        .and_then(|attribute| attribute.get(1).or_else(|| attribute.get(2)))
+        .map(|value| unescape(value.as_str()))
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLC: &str = r#"<?xml version="1.0" encoding="ISO-8859-1"?>
+<SokobanLevels xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <Title>Test &amp; Fun</Title>
+  <Description>
+    Two small levels.
+  </Description>
+  <Email>someone@example.com</Email>
+  <LevelCollection Copyright="Jane Doe" MaxWidth="6" MaxHeight="3">
+    <Level Id="First" Width="5" Height="3">
+      <L>#####</L>
+      <L>#@$.#</L>
+      <L>#####</L>
+    </Level>
+    <Level Id='Second' Width="6" Height="3" Copyright="John Doe">
+      <L>######</L>
+      <L>#+$*.#</L>
+      <L>######</L>
+      <Author>Max</Author>
+      <Comment>Goal in
+the corner</Comment>
+      <Difficulty>easy</Difficulty>
+    </Level>
+  </LevelCollection>
+</SokobanLevels>
+"#;
+
+    #[test]
+    fn test_is_slc() {
+        assert!(is_slc(SLC));
+        assert!(!is_slc("#####\n#@$.#\n#####"));
+    }
+
+    #[test]
+    fn test_parse_collection_info() {
+        let collection = parse(SLC).unwrap();
+        assert_eq!(Some(String::from("Test & Fun")), collection.info.title);
+        assert_eq!(Some(String::from("Two small levels.")), collection.info.description);
+        assert_eq!(None, collection.info.author);
+        assert_eq!(Some(String::from("Jane Doe")), collection.info.copyright);
+    }
+
+    #[test]
+    fn test_parse_levels() {
+        let collection = parse(SLC).unwrap();
+        assert_eq!(2, collection.levels.len());
+        assert_eq!(SlcLevel {
+            first_line: 10,
+            rows: vec!["#####", "#@$.#", "#####"],
+            info: LevelInfo { title: Some(String::from("First")), ..LevelInfo::default() },
+            width: Some(5),
+            height: Some(3),
+        }, collection.levels[0]);
+        assert_eq!(15, collection.levels[1].first_line);
+        assert_eq!(vec!["######", "#+$*.#", "######"], collection.levels[1].rows);
+        assert_eq!(Some(String::from("Second")), collection.levels[1].info.title);
+        assert_eq!(Some(String::from("John Doe")), collection.levels[1].info.copyright);
+        assert_eq!(Some(String::from("Max")), collection.levels[1].info.author);
+        assert_eq!(Some(String::from("Goal in\nthe corner")), collection.levels[1].info.comment);
+        assert_eq!(Some(String::from("easy")), collection.levels[1].info.difficulty);
+    }
+
+    #[test]
+    fn test_parse_without_collection() {
+        assert_eq!(io::ErrorKind::InvalidData, parse("<SokobanLevels></SokobanLevels>").unwrap_err().kind());
+    }
+}
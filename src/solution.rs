@@ -0,0 +1,301 @@
+use std::fmt;
+
+use regex::Regex;
+
+use crate::{LevelSession, Map, MoveDirection};
+use crate::movement::MoveOutcome;
+
+/// Reason why a solution cannot be replayed.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SolutionError {
+    /// The solution contains a character which is neither LURD nor a run length.
+    InvalidCharacter(char),
+    /// The run length with the given digits is too large.
+    InvalidRunLength(String),
+    /// The step with the given index runs into a wall, a block that cannot be pushed or off the map.
+    Blocked(usize),
+    /// The step with the given index pushes a block but is lowercase, or is uppercase but pushes nothing.
+    PushMismatch(usize),
+}
+
+impl fmt::Display for SolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolutionError::InvalidCharacter(c) => write!(f, "unexpected character '{}'", c),
+            SolutionError::InvalidRunLength(count) => write!(f, "run length {} is too large", count),
+            SolutionError::Blocked(step) => write!(f, "step {} is blocked", step + 1),
+            SolutionError::PushMismatch(step) => write!(f, "step {} does not match the push in the solution", step + 1),
+        }
+    }
+}
+
+/// Result of replaying a solution on a level.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SolutionReport {
+    /// Number of steps replayed, including pushes.
+    pub moves: usize,
+    /// Number of replayed steps which pushed a block.
+    pub pushes: usize,
+    /// Whether the level is solved after the replay.
+    pub solves: bool,
+    /// Why the replay stopped early, if it did.
+    pub error: Option<SolutionError>,
+}
+
+impl SolutionReport {
+    /// Returns whether every step of the solution could be replayed.
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl fmt::Display for SolutionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error {
+            Some(error) => write!(f, "invalid, {}", error)?,
+            None if self.solves => write!(f, "solves the level")?,
+            None => write!(f, "valid, but does not solve the level")?,
+        }
+        write!(f, " ({} moves, {} pushes)", self.moves, self.pushes)
+    }
+}
+
+/// Splits a solution file into one solution per level of `maps`, in level order.
+///
+/// Files with SLC-style `<Solution>` elements yield the first solution of each `<Level>`
+/// (`None` for levels without one), or every `<Solution>` if there are no `<Level>` elements.
+/// In plain files each run of consecutive LURD lines is one solution, all other lines separate them.
+/// A LURD line has at least one letter, and a line which is the title of a level names that level
+/// rather than starting a solution.
+/// The last line before a solution names its level: `Level 3` refers to the level numbered 3,
+/// `Level: <title>`, `Title: <title>` or a bare title to the level with that title. A solution without
+/// such a line belongs to the level after the previous solution's. Levels without a solution get `None`.
+pub fn parse_solutions(contents: &str, maps: &[Map]) -> Vec<Option<String>> {
+    let regex_solution = Regex::new(r"(?s)<Solution\b[^>]*>(.*?)</Solution>").unwrap();
+    if regex_solution.is_match(contents) {
+        let regex_level = Regex::new(r"(?s)<Level\b[^>]*?(?:/>|>.*?</Level>)").unwrap();
+        let solution_text = |caps: regex::Captures| caps[1].split_whitespace().collect::<String>();
+        let mut solutions: Vec<Option<String>> = if regex_level.is_match(contents) {
+            regex_level.find_iter(contents)
+                .map(|level| regex_solution.captures(level.as_str()).map(solution_text))
+                .collect()
+        } else {
+            regex_solution.captures_iter(contents).map(|caps| Some(solution_text(caps))).collect()
+        };
+        solutions.resize(maps.len(), None);
+        return solutions;
+    }
+
+    let regex_lurd = Regex::new(r"^[0-9]*[lurdLURD][0-9lurdLURD]*$").unwrap();
+    let is_title = |line: &str| maps.iter().any(|map| map.info.title.as_deref() == Some(line));
+    let mut solutions = vec![None; maps.len()];
+    let mut next_level_idx = 0;
+    let mut header: Option<&str> = None;
+    let mut solution = String::new();
+    for line in contents.lines().map(str::trim).chain(std::iter::once("")) {
+        if regex_lurd.is_match(line) && !(solution.is_empty() && is_title(line)) {
+            solution += line;
+            continue;
+        }
+        if !solution.is_empty() {
+            let level_idx = match header {
+                Some(header) => find_level(maps, header),
+                None => Some(next_level_idx),
+            };
+            if let Some(slot) = level_idx.and_then(|level_idx| solutions.get_mut(level_idx)) {
+                *slot = Some(solution);
+            }
+            next_level_idx = level_idx.map_or(next_level_idx, |level_idx| level_idx + 1);
+            header = None;
+            solution = String::new();
+        }
+        if !line.is_empty() {
+            header = Some(line);
+        }
+    }
+    solutions
+}
+
+/// Finds the level named by a header line of a plain solution file, see [`parse_solutions`].
+fn find_level(maps: &[Map], header: &str) -> Option<usize> {
+    let regex_header = Regex::new(r"(?i)^(?:(level)|title)\s*:?\s*(.*)$").unwrap();
+    let (name, may_be_number) = match regex_header.captures(header) {
+        Some(caps) => (caps.get(2).unwrap().as_str(), caps.get(1).is_some()),
+        None => (header, false),
+    };
+    match name.parse::<u32>() {
+        Ok(number) if may_be_number => maps.iter().position(|map| map.display_number() == number),
+        _ => maps.iter().position(|map| map.info.title.as_deref() == Some(name)),
+    }
+}
+
+/// Replays the LURD string `lurd` on a [`LevelSession`] started on a copy of `map`.
+///
+/// Lowercase letters are moves, uppercase letters pushes, a number repeats the following step
+/// and whitespace is ignored. The replay stops at the first step which does not fit the level.
+pub fn verify_solution(map: &Map, lurd: &str) -> SolutionReport {
+    let mut session = LevelSession::new(map.clone());
+    let mut report = SolutionReport { moves: 0, pushes: 0, solves: false, error: None };
+    let mut count = String::new();
+    'steps: for c in lurd.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            count.push(c);
+            continue;
+        }
+        let movedir = match c.to_ascii_lowercase() {
+            'l' => MoveDirection::Left,
+            'u' => MoveDirection::Up,
+            'r' => MoveDirection::Right,
+            'd' => MoveDirection::Down,
+            _ => {
+                report.error = Some(SolutionError::InvalidCharacter(c));
+                break;
+            }
+        };
+        let repeat = match count.as_str() {
+            "" => 1,
+            digits => match digits.parse::<usize>() {
+                Ok(repeat) => repeat,
+                Err(_) => {
+                    report.error = Some(SolutionError::InvalidRunLength(count));
+                    break;
+                }
+            },
+        };
+        count.clear();
+        for _ in 0..repeat {
+            if let Some(error) = replay_step(&mut session, movedir, c.is_ascii_uppercase(), &mut report) {
+                report.error = Some(error);
+                break 'steps;
+            }
+        }
+    }
+    report.solves = session.map.is_solved();
+    report
+}
+
+fn replay_step(session: &mut LevelSession, movedir: MoveDirection, is_push: bool, report: &mut SolutionReport) -> Option<SolutionError> {
+    let step = report.moves;
+    let outcome = session.apply_move(movedir);
+    if outcome == MoveOutcome::Blocked {
+        return Some(SolutionError::Blocked(step));
+    }
+    report.moves += 1;
+    if outcome.pushed_block().is_some() {
+        report.pushes += 1;
+    }
+    if outcome.pushed_block().is_some() != is_push {
+        return Some(SolutionError::PushMismatch(step));
+    }
+    None
+}
+
+/// Replays the solutions returned by [`parse_solutions`] on the levels in the same order.
+///
+/// Returns the index of each level with a solution together with its report.
+pub fn verify_solutions(maps: &[Map], solutions: &[Option<String>]) -> Vec<(usize, SolutionReport)> {
+    maps.iter().zip(solutions.iter()).enumerate()
+        .filter_map(|(level_idx, (map, solution))| solution.as_ref().map(|lurd| (level_idx, verify_solution(map, lurd))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    fn corridor() -> Map {
+        parse(&["XXXXXXX", "X@ * .X", "XXXXXXX"])
+    }
+
+    fn corridors(nof_levels: u32) -> Vec<Map> {
+        (0..nof_levels).map(|id| Map { id, ..corridor() }).collect()
+    }
+
+    #[test]
+    fn test_verify_solving_solution() {
+        let report = verify_solution(&corridor(), "rRR");
+        assert_eq!(SolutionReport { moves: 3, pushes: 2, solves: true, error: None }, report);
+        assert!(report.is_valid());
+        assert_eq!("solves the level (3 moves, 2 pushes)", report.to_string());
+    }
+
+    #[test]
+    fn test_verify_run_lengths() {
+        assert_eq!(SolutionReport { moves: 3, pushes: 2, solves: true, error: None }, verify_solution(&corridor(), "r2R"));
+    }
+
+    #[test]
+    fn test_verify_unsolved_solution() {
+        let report = verify_solution(&corridor(), "rR\nl");
+        assert!(report.is_valid());
+        assert!(!report.solves);
+        assert_eq!("valid, but does not solve the level (3 moves, 1 pushes)", report.to_string());
+    }
+
+    #[test]
+    fn test_verify_invalid_solutions() {
+        assert_eq!(Some(SolutionError::Blocked(0)), verify_solution(&corridor(), "lrRR").error);
+        assert_eq!(Some(SolutionError::PushMismatch(1)), verify_solution(&corridor(), "rrRR").error);
+        assert_eq!(Some(SolutionError::PushMismatch(0)), verify_solution(&corridor(), "RRR").error);
+        assert_eq!(Some(SolutionError::InvalidCharacter('x')), verify_solution(&corridor(), "rx").error);
+        let report = verify_solution(&corridor(), "r99999999999999999999R");
+        assert_eq!(Some(SolutionError::InvalidRunLength(String::from("99999999999999999999"))), report.error);
+        assert_eq!("invalid, run length 99999999999999999999 is too large (1 moves, 0 pushes)", report.to_string());
+        let report = verify_solution(&corridor(), "rRRR");
+        assert_eq!(Some(SolutionError::Blocked(3)), report.error);
+        assert!(report.solves);
+        assert_eq!("invalid, step 4 is blocked (3 moves, 2 pushes)", report.to_string());
+    }
+
+    #[test]
+    fn test_parse_plain_solutions() {
+        let contents = "Level 1\nrRR\n\nLevel 2\nuuL\nLdd\n";
+        assert_eq!(vec![Some(String::from("rRR")), Some(String::from("uuLLdd"))], parse_solutions(contents, &corridors(2)));
+        assert_eq!(vec![Some(String::from("rRR")), Some(String::from("lL"))], parse_solutions("rRR\n\nlL\n", &corridors(2)));
+    }
+
+    #[test]
+    fn test_parse_plain_solutions_with_gap() {
+        let mut maps = corridors(4);
+        maps[3].info.title = Some(String::from("Last one"));
+        let contents = "; solutions\n\nLevel 1\nrRR\n\nLevel 3\nuuL\n\nLevel: Last one\nlL\n";
+        assert_eq!(vec![Some(String::from("rRR")), None, Some(String::from("uuL")), Some(String::from("lL"))], parse_solutions(contents, &maps));
+        assert_eq!(vec![None, Some(String::from("rRR")), None, None], parse_solutions("Level 2\nrRR\n\nLevel 9\nuU\n", &maps));
+        assert_eq!(vec![None, None, None, Some(String::from("rRR"))], parse_solutions("Last one\nrRR\n", &maps));
+    }
+
+    #[test]
+    fn test_parse_plain_solutions_with_lurd_like_headers() {
+        let mut maps = corridors(3);
+        maps[1].info.title = Some(String::from("Rud"));
+        assert_eq!(vec![None, Some(String::from("rRR")), None], parse_solutions("Rud\nrRR\n", &maps));
+        assert_eq!(vec![Some(String::from("rRRRud")), None, None], parse_solutions("Level 1\nrRR\nRud\n", &maps));
+        assert_eq!(vec![None, None, Some(String::from("uU"))], parse_solutions("2024\n\nLevel 3\nuU\n", &corridors(3)));
+    }
+
+    #[test]
+    fn test_parse_slc_solutions() {
+        let contents = "<LevelCollection>\n<Level Id=\"1\"><L>#####</L>\n<Solution>rR\nR</Solution></Level>\n\
+            <Level Id=\"2\"><L>#####</L></Level>\n<Level Id=\"3\"><Solution Type=\"moves\">ul</Solution></Level>\n</LevelCollection>";
+        let maps = corridors(3);
+        assert_eq!(vec![Some(String::from("rRR")), None, Some(String::from("ul"))], parse_solutions(contents, &maps));
+        assert_eq!(vec![Some(String::from("uU")), None, None], parse_solutions("<Solution>uU</Solution>", &maps));
+    }
+
+    #[test]
+    fn test_parse_slc_solutions_with_empty_rows() {
+        let contents = "<LevelCollection>\n<Level Id=\"1\"><L>#####</L><L/><L>#####</L>\n<Solution>rRR</Solution></Level>\n\
+            <Level Id=\"2\"/>\n<Level Id=\"3\"><L/><Solution>ul</Solution></Level>\n</LevelCollection>";
+        assert_eq!(vec![Some(String::from("rRR")), None, Some(String::from("ul"))], parse_solutions(contents, &corridors(3)));
+    }
+
+    #[test]
+    fn test_verify_solutions() {
+        let maps = vec![corridor(), corridor(), corridor()];
+        let reports = verify_solutions(&maps, &[Some(String::from("rRR")), None, Some(String::from("l"))]);
+        assert_eq!(vec![0, 2], reports.iter().map(|(level_idx, _)| *level_idx).collect::<Vec<usize>>());
+        assert!(reports[0].1.solves);
+        assert!(!reports[1].1.is_valid());
+    }
+}
@@ -0,0 +1,198 @@
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+/// Environment variable with level paths, separated like `PATH`.
+pub const LEVELS_ENV_VAR: &str = "SOKOBAN_LEVELS";
+/// Environment variable overriding the location of the config file.
+pub const CONFIG_ENV_VAR: &str = "SOKOBAN_CONFIG";
+/// File extensions picked up when a directory is given as level source.
+pub const LEVEL_FILE_EXTENSIONS: [&str; 5] = ["txt", "xsb", "sok", "slc", "rle"];
+
+/// Where a collection is read from.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LevelSource {
+    /// A collection file.
+    File(PathBuf),
+    /// Levels piped into standard input.
+    Stdin,
+    /// Levels given as text on the command line.
+    Text(String),
+}
+
+impl fmt::Display for LevelSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelSource::File(path) => write!(f, "{}", path.display()),
+            LevelSource::Stdin => write!(f, "stdin"),
+            LevelSource::Text(_) => write!(f, "level string"),
+        }
+    }
+}
+
+/// Turns command line arguments into level sources.
+///
+/// `-` reads standard input, `--level-string <levels>` takes the levels from the next argument
+/// and every other argument is a file or directory, see [`collection_files`]. Without any
+/// arguments the paths from [`configured_level_paths`] are used.
+pub fn level_sources(args: Vec<OsString>) -> Result<Vec<LevelSource>, io::Error> {
+    let mut sources = Vec::new();
+    let mut args = args.into_iter();
+    let mut has_args = false;
+    while let Some(arg) = args.next() {
+        has_args = true;
+        if arg == "-" {
+            sources.push(LevelSource::Stdin);
+        } else if arg == "--level-string" {
+            let level = args.next()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "--level-string needs the level as next argument"))?;
+            sources.push(LevelSource::Text(level.to_string_lossy().into_owned()));
+        } else {
+            sources.extend(collection_files(&[PathBuf::from(arg)])?.into_iter().map(LevelSource::File));
+        }
+    }
+    if !has_args {
+        sources.extend(collection_files(&configured_level_paths())?.into_iter().map(LevelSource::File));
+    }
+    Ok(sources)
+}
+
+/// Returns the level paths configured outside the command line: the paths in [`LEVELS_ENV_VAR`]
+/// if there are any, otherwise those in the config file.
+///
+/// An empty result means nothing is configured and the shipped collection should be played.
+pub fn configured_level_paths() -> Vec<PathBuf> {
+    let config_paths = config_file_path()
+        .and_then(|path| fs::read_to_string(&path).ok().map(|contents| (path, contents)))
+        .map(|(path, contents)| parse_config(&contents, path.parent().unwrap_or_else(|| Path::new(""))))
+        .unwrap_or_default();
+    select_level_paths(env::var_os(LEVELS_ENV_VAR), config_paths)
+}
+
+/// Picks the paths in the environment variable value if there are any, otherwise the config file paths.
+pub fn select_level_paths(env_value: Option<OsString>, config_paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let env_paths: Vec<PathBuf> = env_value
+        .map(|value| env::split_paths(&value).filter(|path| !path.as_os_str().is_empty()).collect())
+        .unwrap_or_default();
+    if !env_paths.is_empty() {
+        return env_paths;
+    }
+    config_paths
+}
+
+/// Location of the config file: [`CONFIG_ENV_VAR`] if set, otherwise `sokoban/config`
+/// in `$XDG_CONFIG_HOME` or `$HOME/.config`.
+pub fn config_file_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(CONFIG_ENV_VAR) {
+        return Some(PathBuf::from(path));
+    }
+    env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|config_dir| config_dir.join("sokoban").join("config"))
+}
+
+/// Parses the `levels = <path>` lines of a config file, one level source per line.
+///
+/// Empty lines and lines starting with `#` are ignored, relative paths are relative to `base_dir`.
+pub fn parse_config(contents: &str, base_dir: &Path) -> Vec<PathBuf> {
+    let regex_levels = Regex::new(r"^\s*levels\s*=\s*(.*?)\s*$").unwrap();
+    contents.lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| regex_levels.captures(line))
+        .filter(|caps| !caps[1].is_empty())
+        .map(|caps| base_dir.join(&caps[1]))
+        .collect()
+}
+
+/// Expands level paths into collection files: files are kept, directories are replaced
+/// by the files with one of the [`LEVEL_FILE_EXTENSIONS`] they contain, sorted by name.
+pub fn collection_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, io::Error> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut dir_files = Vec::new();
+            for entry in fs::read_dir(path)? {
+                let file = entry?.path();
+                if file.is_file() && has_level_file_extension(&file) {
+                    dir_files.push(file);
+                }
+            }
+            dir_files.sort();
+            files.extend(dir_files);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn has_level_file_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| LEVEL_FILE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn test_level_sources() {
+        assert_eq!(vec![LevelSource::Stdin, LevelSource::Text(String::from("4#|#@$.#")), LevelSource::File(PathBuf::from("a.txt"))],
+            level_sources(args(&["-", "--level-string", "4#|#@$.#", "a.txt"])).unwrap());
+        assert_eq!(io::ErrorKind::InvalidInput, level_sources(args(&["--level-string"])).unwrap_err().kind());
+    }
+
+    #[test]
+    fn test_level_source_display() {
+        assert_eq!("a.txt", LevelSource::File(PathBuf::from("a.txt")).to_string());
+        assert_eq!("stdin", LevelSource::Stdin.to_string());
+        assert_eq!("level string", LevelSource::Text(String::from("#")).to_string());
+    }
+
+    #[test]
+    fn test_select_level_paths() {
+        let config = paths(&["config.txt"]);
+        let env_value = env::join_paths(["b.txt", "levels"]).unwrap();
+        assert_eq!(paths(&["b.txt", "levels"]), select_level_paths(Some(env_value), config.clone()));
+        assert_eq!(config, select_level_paths(Some(OsString::new()), config.clone()));
+        assert_eq!(config, select_level_paths(None, config.clone()));
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let contents = "# my levels\nlevels = collections\n\n  levels=/srv/sokoban/original.xsb  \nlevels =\ntheme = dark\n";
+        assert_eq!(paths(&["/home/jo/.config/sokoban/collections", "/srv/sokoban/original.xsb"]),
+            parse_config(contents, Path::new("/home/jo/.config/sokoban")));
+    }
+
+    #[test]
+    fn test_collection_files() {
+        let dir = env::temp_dir().join(format!("sokoban-sources-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["b.xsb", "a.txt", "notes.md", "c.SLC"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let files = collection_files(&[PathBuf::from("data/maps/maps.txt"), dir.clone()]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(vec![PathBuf::from("data/maps/maps.txt"), dir.join("a.txt"), dir.join("b.xsb"), dir.join("c.SLC")], files.unwrap());
+    }
+
+    #[test]
+    fn test_collection_files_keeps_missing_file() {
+        assert_eq!(paths(&["missing.txt"]), collection_files(&paths(&["missing.txt"])).unwrap());
+    }
+}
@@ -0,0 +1,8 @@
+use crate::Map;
+
+/// Parses a level in the `maps.txt` notation.
+pub(crate) fn parse(map_block: &[&str]) -> Map {
+    let mut map = Map::new();
+    map.parse_map_block(map_block);
+    map
+}
@@ -0,0 +1,238 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+use crate::{Map, MapTile, MoveDirection, Position};
+use crate::movement;
+
+/// Problem found in a level.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ValidationErrorKind {
+    /// There is no `@` or `+`.
+    MissingPlayer,
+    /// There is more than one player.
+    MultiplePlayers(usize),
+    /// The number of blocks and target zones differ.
+    BlockTargetMismatch {
+        /// Number of blocks.
+        blocks: usize,
+        /// Number of target zones.
+        targets: usize,
+    },
+    /// The player can walk off the map starting from the given position.
+    OpenWall(Position),
+    /// The player cannot reach the block at the given position.
+    UnreachableBlock(Position),
+    /// The player cannot reach the target zone at the given position.
+    UnreachableTarget(Position),
+    /// The `Size X:` and `Size Y:` header lines do not match the level.
+    SizeMismatch {
+        /// Width from the header.
+        declared_width: usize,
+        /// Height from the header.
+        declared_height: usize,
+        /// Actual width of the level.
+        width: usize,
+        /// Actual height of the level.
+        height: usize,
+    },
+}
+
+/// A problem together with the level and the line it was found in.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ValidationError {
+    /// Index of the level across all loaded collections, the same as its [`Map::id`].
+    pub level_idx: usize,
+    /// Number of the level shown to the player, see [`Map::display_number`].
+    pub level_number: u32,
+    /// Line number in the source text, starting at 1.
+    pub line: usize,
+    /// What is wrong.
+    pub kind: ValidationErrorKind,
+}
+
+impl fmt::Display for ValidationErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationErrorKind::MissingPlayer => write!(f, "level has no player"),
+            ValidationErrorKind::MultiplePlayers(count) => write!(f, "level has {} players", count),
+            ValidationErrorKind::BlockTargetMismatch { blocks, targets } => write!(f, "level has {} blocks but {} target zones", blocks, targets),
+            ValidationErrorKind::OpenWall(position) => write!(f, "outer wall has a gap near column {}", position.x + 1),
+            ValidationErrorKind::UnreachableBlock(position) => write!(f, "block in column {} cannot be reached by the player", position.x + 1),
+            ValidationErrorKind::UnreachableTarget(position) => write!(f, "target zone in column {} cannot be reached by the player", position.x + 1),
+            ValidationErrorKind::SizeMismatch { declared_width, declared_height, width, height } =>
+                write!(f, "header declares size {}x{} but level is {}x{}", declared_width, declared_height, width, height),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "level {}, line {}: {}", self.level_number, self.line, self.kind)
+    }
+}
+
+/// Checks the player count, the block/target balance, the closure of the outer wall,
+/// whether all blocks and targets can be reached and whether the declared size in
+/// [`Map::metadata`] fits the level.
+///
+/// `map_block` are the raw rows of the level, `map` the level parsed from them with its id
+/// already set and `first_line` the line number of the first row.
+pub fn validate_map_block(map_block: &[&str], map: &Map, first_line: usize) -> Result<(), Vec<ValidationError>> {
+    let mut errors = Vec::new();
+    let (level_idx, level_number) = (map.id as usize, map.display_number());
+    let mut add_error = |y: usize, kind: ValidationErrorKind| errors.push(ValidationError { level_idx, level_number, line: first_line + y, kind });
+
+    let player_lines: Vec<usize> = map_block.iter().enumerate()
+        .flat_map(|(y, line)| line.chars().filter(|c| *c == '@' || *c == '+').map(move |_| y))
+        .collect();
+    match player_lines.len() {
+        0 => add_error(0, ValidationErrorKind::MissingPlayer),
+        1 => (),
+        count => add_error(player_lines[1], ValidationErrorKind::MultiplePlayers(count)),
+    }
+
+    let targets = find_targets(map);
+    if targets.len() != map.movable_blocks.len() {
+        add_error(0, ValidationErrorKind::BlockTargetMismatch { blocks: map.movable_blocks.len(), targets: targets.len() });
+    }
+
+    if !player_lines.is_empty() {
+        let (reachable, gap) = flood_fill(map, &map.player_position);
+        if let Some(position) = gap {
+            add_error(position.y as usize, ValidationErrorKind::OpenWall(position));
+        }
+        for block in map.movable_blocks.iter().filter(|block| !reachable.contains(&block.position)) {
+            add_error(block.position.y as usize, ValidationErrorKind::UnreachableBlock(block.position));
+        }
+        for target in targets.iter().filter(|target| !reachable.contains(target)) {
+            add_error(target.y as usize, ValidationErrorKind::UnreachableTarget(*target));
+        }
+    }
+
+    if let Some(metadata) = &map.metadata {
+        let declared_width = metadata.size_x.unwrap_or_else(|| map.width());
+        let declared_height = metadata.size_y.unwrap_or_else(|| map.height());
+        if (declared_width, declared_height) != (map.width(), map.height()) {
+            add_error(0, ValidationErrorKind::SizeMismatch { declared_width, declared_height, width: map.width(), height: map.height() });
+        }
+    }
+
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+fn find_targets(map: &Map) -> Vec<Position> {
+    let mut targets = Vec::new();
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let position = Position { x: x as i32, y: y as i32 };
+            if map.get_tile_type_for_position(&position) == MapTile::TargetZone {
+                targets.push(position);
+            }
+        }
+    }
+    targets
+}
+
+fn flood_fill(map: &Map, start: &Position) -> (HashSet<Position>, Option<Position>) {
+    let mut reachable = HashSet::new();
+    let mut gap = None;
+    let mut queue = VecDeque::new();
+    reachable.insert(*start);
+    queue.push_back(*start);
+    while let Some(position) = queue.pop_front() {
+        for movedir in MoveDirection::ALL.iter() {
+            let next = movement::calc_new_position_after_movement(movedir, &position);
+            match map.get_tile_type_for_position(&next) {
+                MapTile::Wall => (),
+                MapTile::Outside => {
+                    if gap.is_none() {
+                        gap = Some(position);
+                    }
+                }
+                _ => {
+                    if reachable.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+    }
+    (reachable, gap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::LevelMetadata;
+
+    fn validate(map_block: &[&str]) -> Result<(), Vec<ValidationError>> {
+        let mut map = Map::new();
+        map.parse_map_block(map_block);
+        map.id = 2;
+        validate_map_block(map_block, &map, 10)
+    }
+
+    fn error_kinds(map_block: &[&str]) -> Vec<(usize, ValidationErrorKind)> {
+        validate(map_block).unwrap_err().into_iter().map(|error| (error.line, error.kind)).collect()
+    }
+
+    #[test]
+    fn test_valid_level() {
+        assert!(validate(&["XXXXXX", "X@*.&X", "XXXXXX"]).is_ok());
+    }
+
+    #[test]
+    fn test_missing_player() {
+        assert_eq!(vec![(10, ValidationErrorKind::MissingPlayer)], error_kinds(&["XXXXX", "X *.X", "XXXXX"]));
+    }
+
+    #[test]
+    fn test_multiple_players() {
+        assert_eq!(vec![(12, ValidationErrorKind::MultiplePlayers(2))], error_kinds(&["XXXXX", "X@*.X", "X  @X", "XXXXX"]));
+    }
+
+    #[test]
+    fn test_block_target_mismatch() {
+        assert_eq!(
+            vec![(10, ValidationErrorKind::BlockTargetMismatch { blocks: 2, targets: 1 })],
+            error_kinds(&["XXXXXX", "X@**.X", "XXXXXX"]));
+    }
+
+    #[test]
+    fn test_open_wall() {
+        assert_eq!(
+            vec![(11, ValidationErrorKind::OpenWall(Position { x: 4, y: 1 }))],
+            error_kinds(&["XXXXX", "X@*. ", "XXXXX"]));
+    }
+
+    #[test]
+    fn test_unreachable_block_and_target() {
+        assert_eq!(
+            vec![
+                (11, ValidationErrorKind::UnreachableBlock(Position { x: 5, y: 1 })),
+                (11, ValidationErrorKind::UnreachableTarget(Position { x: 6, y: 1 })),
+            ],
+            error_kinds(&["XXXXXXXX", "X@*.X*.X", "XXXXXXXX"]));
+    }
+
+    #[test]
+    fn test_size_mismatch() {
+        let map_block = ["XXXXX", "X@*.X", "XXXXX"];
+        let mut map = Map::new();
+        map.parse_map_block(&map_block);
+        map.metadata = Some(LevelMetadata { size_x: Some(5), size_y: Some(3), ..LevelMetadata::default() });
+        assert!(validate_map_block(&map_block, &map, 1).is_ok());
+        map.metadata = Some(LevelMetadata { size_x: Some(6), ..LevelMetadata::default() });
+        let errors = validate_map_block(&map_block, &map, 1).unwrap_err();
+        assert_eq!(ValidationErrorKind::SizeMismatch { declared_width: 6, declared_height: 3, width: 5, height: 3 }, errors[0].kind);
+        assert_eq!("header declares size 6x3 but level is 5x3", errors[0].kind.to_string());
+    }
+
+    #[test]
+    fn test_display() {
+        let error = ValidationError { level_idx: 2, level_number: 3, line: 12, kind: ValidationErrorKind::MultiplePlayers(2) };
+        assert_eq!("level 3, line 12: level has 2 players", error.to_string());
+        let errors = validate(&["XXXXX", "X *.X", "XXXXX"]).unwrap_err();
+        assert_eq!((2, 3), (errors[0].level_idx, errors[0].level_number));
+    }
+}
@@ -0,0 +1,273 @@
+use regex::Regex;
+
+use crate::info::{CollectionInfo, LevelInfo};
+use crate::map::{Map, MapFormat};
+
+/// A level of an XSB collection.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct XsbLevel<'a> {
+    /// Line number of the first row, starting at 1.
+    pub first_line: usize,
+    /// The rows of the level.
+    pub rows: Vec<&'a str>,
+    /// Information from the `Key: value` lines following the rows.
+    pub info: LevelInfo,
+}
+
+/// Contents of an XSB file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct XsbCollection<'a> {
+    /// Information from the `Key: value` lines before the first level.
+    pub info: CollectionInfo,
+    /// The levels in file order.
+    pub levels: Vec<XsbLevel<'a>>,
+}
+
+/// Compiles the pattern matching level rows in XSB notation.
+///
+/// Rows consist of `#`, `$`, `*`, `.`, `@`, `+` and floor characters (space, `-`, `_`)
+/// and contain at least one wall.
+pub fn regex_row() -> Regex {
+    Regex::new(r"^[ \-_]*#[ #@+$*.\-_]*$").unwrap()
+}
+
+/// Splits XSB text into levels.
+///
+/// A level is a run of consecutive rows, any other line (titles, comments, blank lines)
+/// separates levels. Returns the line number of the first row, starting at 1, and the rows.
+pub fn split_levels(map_contents: &str) -> Vec<(usize, Vec<&str>)> {
+    let regex_row = regex_row();
+    let mut levels = Vec::new();
+    let mut map_block: Vec<&str> = Vec::new();
+    let mut first_line = 0;
+    for (line_idx, line) in map_contents.lines().enumerate() {
+        if regex_row.is_match(line) {
+            if map_block.is_empty() {
+                first_line = line_idx + 1;
+            }
+            map_block.push(line);
+        } else if !map_block.is_empty() {
+            levels.push((first_line, map_block));
+            map_block = Vec::new();
+        }
+    }
+    if !map_block.is_empty() {
+        levels.push((first_line, map_block));
+    }
+    levels
+}
+
+/// Splits XSB text into levels like [`split_levels`] and reads the information lines.
+///
+/// `Title:`, `Description:`, `Author:` and `Copyright:` lines before the first level describe the
+/// collection. `Title:`, `Author:`, `Copyright:`, `Comment:` and `Difficulty:` lines after the rows of a
+/// level describe that level. A `Comment:` line without text starts a comment spanning all lines
+/// up to `Comment-End:`. If no `Comment-End:` follows before the next `Comment:` line, the comment
+/// ends before the next level instead.
+pub fn parse_collection(map_contents: &str) -> XsbCollection<'_> {
+    let regex_row = regex_row();
+    let regex_key = Regex::new(r"(?i)^\s*(Title|Description|Author|Copyright|Comment|Difficulty)\s*:\s*(.*?)\s*$").unwrap();
+    let regex_comment_end = Regex::new(r"(?i)^\s*Comment[-_ ]?End\s*:?").unwrap();
+    let regex_comment_start = Regex::new(r"(?i)^\s*Comment\s*:").unwrap();
+
+    let lines: Vec<&str> = map_contents.lines().collect();
+    let mut collection = XsbCollection { info: CollectionInfo::default(), levels: Vec::new() };
+    let mut map_block: Vec<&str> = Vec::new();
+    let mut first_line = 0;
+    let mut comment: Option<(Vec<&str>, bool)> = None;
+    for (line_idx, line) in lines.iter().copied().enumerate() {
+        if let Some((comment_lines, has_end)) = comment.as_mut() {
+            if *has_end && regex_comment_end.is_match(line) {
+                set_comment(&mut collection, comment_lines);
+                comment = None;
+                continue;
+            } else if *has_end || !regex_row.is_match(line) {
+                comment_lines.push(line);
+                continue;
+            }
+            set_comment(&mut collection, comment_lines);
+            comment = None;
+        }
+        if regex_row.is_match(line) {
+            if map_block.is_empty() {
+                first_line = line_idx + 1;
+            }
+            map_block.push(line);
+        } else {
+            if !map_block.is_empty() {
+                collection.levels.push(XsbLevel { first_line, rows: map_block, info: LevelInfo::default() });
+                map_block = Vec::new();
+            }
+            if let Some(caps) = regex_key.captures(line) {
+                let key = caps[1].to_ascii_lowercase();
+                let value = Some(caps[2].to_string()).filter(|value| !value.is_empty());
+                match collection.levels.last_mut() {
+                    Some(_) if key == "comment" && value.is_none() => {
+                        let has_end = lines[line_idx + 1..].iter()
+                            .take_while(|line| !regex_comment_start.is_match(line))
+                            .any(|line| regex_comment_end.is_match(line));
+                        comment = Some((Vec::new(), has_end));
+                    }
+                    Some(level) => set_level_info(&mut level.info, &key, value),
+                    None => set_collection_info(&mut collection.info, &key, value),
+                }
+            }
+        }
+    }
+    if let Some((comment_lines, _)) = comment.as_mut() {
+        set_comment(&mut collection, comment_lines);
+    }
+    if !map_block.is_empty() {
+        collection.levels.push(XsbLevel { first_line, rows: map_block, info: LevelInfo::default() });
+    }
+    collection
+}
+
+/// Sets the comment of the last level read, without the blank lines at its end.
+fn set_comment(collection: &mut XsbCollection, comment_lines: &mut Vec<&str>) {
+    while comment_lines.last().is_some_and(|line| line.trim().is_empty()) {
+        comment_lines.pop();
+    }
+    if let Some(level) = collection.levels.last_mut() {
+        level.info.comment = Some(comment_lines.join("\n")).filter(|comment| !comment.is_empty());
+    }
+}
+
+fn set_level_info(info: &mut LevelInfo, key: &str, value: Option<String>) {
+    match key {
+        "title" => info.title = value,
+        "author" => info.author = value,
+        "copyright" => info.copyright = value,
+        "comment" => info.comment = value,
+        "difficulty" => info.difficulty = value,
+        _ => (),
+    }
+}
+
+fn set_collection_info(info: &mut CollectionInfo, key: &str, value: Option<String>) {
+    match key {
+        "title" => info.title = value,
+        "description" => info.description = value,
+        "author" => info.author = value,
+        "copyright" => info.copyright = value,
+        _ => (),
+    }
+}
+
+/// Writes a single level in XSB notation.
+pub fn write_map(map: &Map) -> String {
+    map.write_rows(MapFormat::Xsb).iter().map(|row| format!("{}\n", row)).collect()
+}
+
+/// Writes a collection in XSB notation, each level preceded by a `; <number>` comment
+/// and followed by the information lines of its [`LevelInfo`].
+pub fn write_maps(maps: &[Map]) -> String {
+    maps.iter().map(|map| format!("; {}\n\n{}{}\n", map.display_number(), write_map(map), write_info(&map.info))).collect()
+}
+
+fn write_info(info: &LevelInfo) -> String {
+    let mut lines = String::new();
+    for (key, value) in [("Title", &info.title), ("Author", &info.author), ("Copyright", &info.copyright), ("Difficulty", &info.difficulty)] {
+        if let Some(value) = value {
+            lines += &format!("{}: {}\n", key, value);
+        }
+    }
+    match &info.comment {
+        Some(comment) if comment.contains('\n') => lines += &format!("Comment:\n{}\nComment-End:\n", comment),
+        Some(comment) => lines += &format!("Comment: {}\n", comment),
+        None => (),
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regex_row() {
+        let regex_row = regex_row();
+        assert!(regex_row.is_match("#####"));
+        assert!(regex_row.is_match("  #@$.*+ #"));
+        assert!(regex_row.is_match("--#__#"));
+        assert!(!regex_row.is_match(""));
+        assert!(!regex_row.is_match("   "));
+        assert!(!regex_row.is_match("; comment #1"));
+        assert!(!regex_row.is_match("Title: #1"));
+        assert!(!regex_row.is_match("XXXXX"));
+    }
+
+    const XSB: &str = "Title: Demo\nAuthor: Jo\nDescription: Two levels\n\n; 1\n#####\n#@$.#\n#####\nTitle: Start\n\
+        Difficulty: easy\nComment: warm up\n\n; 2\n#####\n#+$*#\n#####\nAuthor: Max\nComment:\nfirst line\n#####\nComment-End:\n";
+
+    #[test]
+    fn test_parse_collection_info() {
+        let collection = parse_collection(XSB);
+        assert_eq!(CollectionInfo { title: Some(String::from("Demo")), description: Some(String::from("Two levels")),
+            author: Some(String::from("Jo")), copyright: None }, collection.info);
+    }
+
+    #[test]
+    fn test_parse_collection_levels() {
+        let collection = parse_collection(XSB);
+        assert_eq!(2, collection.levels.len());
+        assert_eq!(XsbLevel {
+            first_line: 6,
+            rows: vec!["#####", "#@$.#", "#####"],
+            info: LevelInfo { title: Some(String::from("Start")), difficulty: Some(String::from("easy")),
+                comment: Some(String::from("warm up")), ..LevelInfo::default() },
+        }, collection.levels[0]);
+        assert_eq!(vec!["#####", "#+$*#", "#####"], collection.levels[1].rows);
+        assert_eq!(LevelInfo { author: Some(String::from("Max")), comment: Some(String::from("first line\n#####")), ..LevelInfo::default() },
+            collection.levels[1].info);
+    }
+
+    #[test]
+    fn test_parse_unterminated_comment() {
+        let xsb = "#####\n#@$.#\n#####\nComment:\nno end\n\n; 2\n#####\n#+$*#\n#####\nTitle: Second\nComment:\nopen\n";
+        let collection = parse_collection(xsb);
+        assert_eq!(2, collection.levels.len());
+        assert_eq!(Some(String::from("no end\n\n; 2")), collection.levels[0].info.comment);
+        assert_eq!(vec!["#####", "#+$*#", "#####"], collection.levels[1].rows);
+        assert_eq!(Some(String::from("Second")), collection.levels[1].info.title);
+        assert_eq!(Some(String::from("open")), collection.levels[1].info.comment);
+    }
+
+    #[test]
+    fn test_write_info_round_trip() {
+        let collection = parse_collection(XSB);
+        let mut maps = Vec::new();
+        for level in collection.levels.iter() {
+            let mut map = Map::new();
+            map.parse_map_block_with_format(&level.rows, MapFormat::Xsb);
+            map.info = level.info.clone();
+            maps.push(map);
+        }
+        let written = write_maps(&maps);
+        let infos: Vec<LevelInfo> = parse_collection(&written).levels.into_iter().map(|level| level.info).collect();
+        assert_eq!(collection.levels.into_iter().map(|level| level.info).collect::<Vec<LevelInfo>>(), infos);
+    }
+
+    #[test]
+    fn test_write_map() {
+        let mut map = Map::new();
+        map.parse_map_block(&["XXXX", "X@&X", "XXXX"]);
+        assert_eq!("####\n#@*#\n####\n", write_map(&map));
+    }
+
+    #[test]
+    fn test_write_maps() {
+        let mut map = Map::new();
+        map.parse_map_block(&["XXX", "X+X", "XXX"]);
+        map.id = 4;
+        assert_eq!("; 5\n\n###\n#+#\n###\n\n", write_maps(&[map]));
+    }
+
+    #[test]
+    fn test_split_levels() {
+        let levels = split_levels("; first\n####\n#@.#\n####\n\nTitle: second\n###\n#+#\n###");
+        assert_eq!(2, levels.len());
+        assert_eq!((2, vec!["####", "#@.#", "####"]), levels[0]);
+        assert_eq!((7, vec!["###", "#+#", "###"]), levels[1]);
+    }
+}